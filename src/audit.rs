@@ -0,0 +1,192 @@
+use crate::error::CommitSenseError;
+use crate::openai::AISuggestion;
+use crate::provider::{ChatMessage, CompletionRequest, CompletionResponse, LlmProvider};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The `prev_hash` of the first record of a log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One request sent to a model during a run, and what came back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Exchange {
+    /// The provider the request was sent to.
+    pub provider: String,
+    /// The model the request was sent with.
+    pub model: String,
+    /// The full conversation sent, after redaction.
+    pub messages: Vec<ChatMessage>,
+    /// The raw text of the model's reply, if the request succeeded.
+    pub response: Option<String>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Collects every exchange sent through an `AuditingProvider`. Clones share the exchanges.
+#[derive(Debug, Clone, Default)]
+pub struct AuditTrail {
+    exchanges: Arc<Mutex<Vec<Exchange>>>,
+}
+
+impl AuditTrail {
+    /// Returns the exchanges recorded so far, in the order they were made.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().expect("audit trail lock is not poisoned").clone()
+    }
+
+    fn record(&self, exchange: Exchange) {
+        self.exchanges.lock().expect("audit trail lock is not poisoned").push(exchange);
+    }
+}
+
+/// Wraps another provider and records every request and its response or error in a trail.
+pub struct AuditingProvider {
+    inner: Box<dyn LlmProvider>,
+    trail: AuditTrail,
+}
+
+impl AuditingProvider {
+    /// Creates a provider that records the calls to `inner` in `trail`.
+    pub fn new(inner: Box<dyn LlmProvider>, trail: AuditTrail) -> Self {
+        AuditingProvider { inner, trail }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AuditingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let result = self.inner.complete(request).await;
+        let (response, error) = match &result {
+            Ok(response) => (Some(response.content.clone()), None),
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+        self.trail.record(Exchange {
+            provider: self.inner.name().to_string(),
+            model: request.model.clone(),
+            messages: request.messages.clone(),
+            response,
+            error,
+        });
+        result
+    }
+}
+
+/// One line of the audit log: everything sent to and received from the models in a run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditRecord {
+    /// When the run finished, in RFC 3339 format.
+    pub timestamp: String,
+    /// The repository the run analyzed (`GITHUB_REPOSITORY` in Actions, otherwise the project path).
+    pub repository: String,
+    /// The commit the analyzed range starts after.
+    pub base_oid: String,
+    /// The last commit of the analyzed range.
+    pub head_oid: String,
+    /// The model that produced the suggestion (`conventional` when analyzed offline).
+    pub model: String,
+    /// Every request of the run, including repairs, batches, samples, fallbacks and follow-ups.
+    pub exchanges: Vec<Exchange>,
+    /// The validated suggestion, including the corrections applied to the model's answer.
+    pub suggestion: Option<AISuggestion>,
+    /// Why the run failed, if it did.
+    pub error: Option<String>,
+    /// The `hash` of the previous record, or `GENESIS_HASH` for the first.
+    #[serde(default)]
+    pub prev_hash: String,
+    /// SHA-256 of the record without this field, covering `prev_hash`.
+    #[serde(default)]
+    pub hash: String,
+}
+
+/// Returns the hash of `record`: SHA-256 of its JSON with sorted keys and without the `hash` field.
+fn record_hash(record: &serde_json::Value) -> String {
+    let mut record = record.clone();
+    if let Some(fields) = record.as_object_mut() {
+        fields.remove("hash");
+    }
+    // serde_json::Value keeps object keys sorted, so the serialization is canonical
+    format!("{:x}", Sha256::digest(record.to_string().as_bytes()))
+}
+
+/// Returns the hash of the last record of the log at `path`, or `GENESIS_HASH` if there is none.
+fn last_hash(path: &Path) -> Result<String> {
+    if !path.exists() {
+        return Ok(GENESIS_HASH.to_string());
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read audit log '{}'", path.display()))?;
+    let Some(line) = content.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(GENESIS_HASH.to_string());
+    };
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|record| record.get("hash").and_then(|hash| hash.as_str()).map(str::to_string))
+        .ok_or_else(|| {
+            CommitSenseError::Config(format!(
+                "The last record of audit log '{}' is unreadable, so the hash chain can't be extended",
+                path.display()
+            ))
+            .into()
+        })
+}
+
+/// Chains `record` to the last record of the log at `path` and appends it as one JSON line,
+/// creating the file if needed. Returns the record's hash.
+pub fn append_audit_record(path: &Path, mut record: AuditRecord) -> Result<String> {
+    record.prev_hash = last_hash(path)?;
+    record.hash = String::new();
+    let mut value = serde_json::to_value(&record).context("Failed to serialize audit record")?;
+    let hash = record_hash(&value);
+    value["hash"] = serde_json::Value::String(hash.clone());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open audit log '{}'", path.display()))?;
+    writeln!(file, "{}", value).with_context(|| format!("Failed to write audit log '{}'", path.display()))?;
+    Ok(hash)
+}
+
+/// Checks the hash chain of the audit log at `path` and returns the number of records and the
+/// hash of the last one. Fails at the first record that was changed, or that doesn't follow the
+/// record before it because records were removed, reordered or inserted.
+///
+/// Records cut off the end of the log leave a valid chain; compare the last hash with one kept
+/// elsewhere (e.g. in the CI log of the last run) to detect that.
+pub fn verify_audit_log(path: &Path) -> Result<(usize, String)> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read audit log '{}'", path.display()))?;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut records = 0;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tampered = |reason: &str| anyhow::anyhow!("Audit log '{}' fails verification at line {}: {}", path.display(), i + 1, reason);
+        let record: serde_json::Value = serde_json::from_str(line).map_err(|_| tampered("not a JSON record"))?;
+        if record.get("prev_hash").and_then(|h| h.as_str()) != Some(prev_hash.as_str()) {
+            return Err(tampered("it doesn't follow the previous record"));
+        }
+        let hash = record_hash(&record);
+        if record.get("hash").and_then(|h| h.as_str()) != Some(hash.as_str()) {
+            return Err(tampered("its content doesn't match its hash"));
+        }
+        prev_hash = hash;
+        records += 1;
+    }
+    Ok((records, prev_hash))
+}
@@ -0,0 +1,115 @@
+use crate::openai::AISuggestion;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the layout of cache entries or keys changes, so old entries are ignored.
+const CACHE_FORMAT: u32 = 2;
+
+/// Identifies one analysis: the same key must always yield the same suggestion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey(String);
+
+/// The inputs hashed into a `CacheKey`.
+#[derive(Serialize)]
+struct KeyMaterial<'a> {
+    format: u32,
+    backend: &'a str,
+    model: &'a str,
+    prompt_template: &'a str,
+    current_version: &'a str,
+    commit_oids: &'a [String],
+}
+
+impl CacheKey {
+    /// Hashes the backend serving the model (provider and endpoint), the model, prompt template,
+    /// current version and the ordered commit OIDs.
+    pub fn new(
+        backend: &str,
+        model: &str,
+        prompt_template: &str,
+        current_version: &str,
+        commit_oids: &[String],
+    ) -> Self {
+        let material = KeyMaterial {
+            format: CACHE_FORMAT,
+            backend,
+            model,
+            prompt_template,
+            current_version,
+            commit_oids,
+        };
+        // Serializing to JSON keeps field boundaries unambiguous before hashing
+        let bytes = serde_json::to_vec(&material).expect("cache key material is serializable");
+        CacheKey(format!("{:x}", Sha256::digest(&bytes)))
+    }
+
+    /// Returns the key as a hex string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Stores validated suggestions on disk so re-runs on the same commits don't call the API again.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// Creates a cache rooted at `dir`. The directory is created on the first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ResponseCache { dir: dir.into() }
+    }
+
+    /// Returns the default cache location inside the repository's git directory.
+    pub fn default_dir(git_dir: &Path) -> PathBuf {
+        git_dir.join("commit-sense").join("cache")
+    }
+
+    /// Returns the directory holding the cache entries.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.as_str()))
+    }
+
+    /// Looks up a suggestion. Unreadable or corrupt entries are treated as misses.
+    pub fn get(&self, key: &CacheKey) -> Option<AISuggestion> {
+        let path = self.entry_path(key);
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(suggestion) => {
+                debug!("Cache hit for key {} ('{}')", key.as_str(), path.display());
+                Some(suggestion)
+            }
+            Err(e) => {
+                warn!("Ignoring corrupt cache entry '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Stores a suggestion, replacing any existing entry for the key.
+    pub fn put(&self, key: &CacheKey, suggestion: &AISuggestion) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory '{}'", self.dir.display()))?;
+
+        // Write to a temporary file first so a concurrent reader never sees a partial entry
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let content = serde_json::to_string_pretty(suggestion).context("Failed to serialize suggestion")?;
+        fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to write cache entry '{}'", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write cache entry '{}'", path.display()))?;
+
+        debug!("Cached suggestion under key {}", key.as_str());
+        Ok(())
+    }
+}
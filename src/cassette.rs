@@ -0,0 +1,244 @@
+use crate::error::CommitSenseError;
+use crate::provider::{ChatMessage, CompletionRequest, CompletionResponse, LlmProvider, TokenUsage};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Bumped whenever the layout of fixtures or the key normalization changes, so old cassettes miss.
+const CASSETTE_FORMAT: u32 = 1;
+
+/// A request with volatile formatting removed, as hashed into its key and stored in its fixture.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NormalizedRequest {
+    /// The fixture layout version.
+    pub format: u32,
+    /// The model identifier.
+    pub model: String,
+    /// The conversation, with line endings and trailing whitespace normalized.
+    pub messages: Vec<ChatMessage>,
+    /// Sampling temperature, if set.
+    pub temperature: Option<f32>,
+    /// Sampling seed, if set.
+    pub seed: Option<u64>,
+    /// Limit on generated tokens, if set.
+    pub max_tokens: Option<u32>,
+    /// The JSON schema the reply was constrained to, if any.
+    pub response_schema: Option<serde_json::Value>,
+}
+
+impl NormalizedRequest {
+    /// Normalizes `request`: line endings become `\n`, and trailing whitespace is dropped from every
+    /// line and message, so incidental formatting doesn't change the key.
+    pub fn new(request: &CompletionRequest) -> Self {
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| ChatMessage {
+                role: message.role.to_lowercase(),
+                content: normalize_text(&message.content),
+            })
+            .collect();
+        NormalizedRequest {
+            format: CASSETTE_FORMAT,
+            model: request.model.clone(),
+            messages,
+            temperature: request.temperature,
+            seed: request.seed,
+            max_tokens: request.max_tokens,
+            response_schema: request.response_schema.as_ref().map(|s| {
+                serde_json::json!({ "name": s.name, "schema": s.schema })
+            }),
+        }
+    }
+
+    /// Returns the SHA-256 hex digest identifying the request.
+    pub fn key(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("normalized request is serializable");
+        format!("{:x}", Sha256::digest(&bytes))
+    }
+}
+
+/// Normalizes line endings and trailing whitespace.
+fn normalize_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+/// One response of a fixture.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedResponse {
+    /// The text of the assistant's reply.
+    pub content: String,
+    /// Token counts, if the backend reported them.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+}
+
+/// A recorded request and the responses it received, in order. Identical requests made more than
+/// once in a run (e.g. with `--samples`) are answered by successive responses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fixture {
+    /// The request, for reading and diffing the cassette.
+    pub request: NormalizedRequest,
+    /// The responses, in the order they were received.
+    pub responses: Vec<RecordedResponse>,
+}
+
+/// Returns the path of the fixture with `key` in the cassette directory `dir`.
+pub fn fixture_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.json", key))
+}
+
+/// Returns how many times `key` was seen before in this run, and counts this time.
+fn next_index(seen: &Mutex<HashMap<String, usize>>, key: &str) -> usize {
+    let mut seen = seen.lock().expect("cassette lock is not poisoned");
+    let count = seen.entry(key.to_string()).or_insert(0);
+    *count += 1;
+    *count - 1
+}
+
+/// Wraps another provider and saves every successful request and response to a cassette directory.
+///
+/// A fixture is overwritten the first time its request is made in a run, so re-recording replaces
+/// stale responses instead of appending to them.
+pub struct RecordingProvider {
+    inner: Box<dyn LlmProvider>,
+    dir: PathBuf,
+    seen: Mutex<HashMap<String, usize>>,
+}
+
+impl RecordingProvider {
+    /// Creates a provider that records the responses of `inner` in `dir`.
+    pub fn new(inner: Box<dyn LlmProvider>, dir: impl Into<PathBuf>) -> Self {
+        RecordingProvider {
+            inner,
+            dir: dir.into(),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn record(&self, request: NormalizedRequest, response: &CompletionResponse) -> Result<()> {
+        let key = request.key();
+        let path = fixture_path(&self.dir, &key);
+        let recorded = RecordedResponse {
+            content: response.content.clone(),
+            usage: response.usage,
+        };
+        let fixture = match next_index(&self.seen, &key) {
+            0 => Fixture {
+                request,
+                responses: vec![recorded],
+            },
+            _ => {
+                let mut fixture = read_fixture(&path)?;
+                fixture.responses.push(recorded);
+                fixture
+            }
+        };
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cassette directory '{}'", self.dir.display()))?;
+        let content = serde_json::to_string_pretty(&fixture).context("Failed to serialize fixture")?;
+        fs::write(&path, content + "\n")
+            .with_context(|| format!("Failed to write fixture '{}'", path.display()))?;
+        debug!("Recorded response {} of request {}", fixture.responses.len(), key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RecordingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let response = self.inner.complete(request).await?;
+        self.record(NormalizedRequest::new(request), &response)?;
+        Ok(response)
+    }
+}
+
+/// Answers requests from a cassette directory without any network access.
+///
+/// A request that was not recorded fails with a configuration error instead of being treated as
+/// an API failure, so a stale cassette can't silently fall back to another model or the offline rules.
+pub struct ReplayProvider {
+    dir: PathBuf,
+    seen: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayProvider {
+    /// Creates a provider serving the fixtures in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ReplayProvider {
+            dir: dir.into(),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    /// Every recording provider supports structured output, so the replayed requests ask for it too.
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let key = NormalizedRequest::new(request).key();
+        let path = fixture_path(&self.dir, &key);
+        if !path.exists() {
+            return Err(CommitSenseError::Config(format!(
+                "No recorded response for request {} (model '{}') in '{}'. Re-record the cassette with --llm-record.",
+                key,
+                request.model,
+                self.dir.display()
+            ))
+            .into());
+        }
+        let fixture = read_fixture(&path)?;
+        let index = next_index(&self.seen, &key);
+        let recorded = fixture.responses.get(index).ok_or_else(|| {
+            CommitSenseError::Config(format!(
+                "Request {} was made {} times, but only {} response(s) were recorded in '{}'. Re-record the cassette with --llm-record.",
+                key,
+                index + 1,
+                fixture.responses.len(),
+                path.display()
+            ))
+        })?;
+        info!("Replaying response {} of request {}", index + 1, key);
+        Ok(CompletionResponse {
+            content: recorded.content.clone(),
+            usage: recorded.usage,
+        })
+    }
+}
+
+/// Reads the fixture at `path`.
+fn read_fixture(path: &Path) -> Result<Fixture> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read fixture '{}'", path.display()))?;
+    serde_json::from_str(&content).map_err(|e| {
+        CommitSenseError::Config(format!("Invalid fixture '{}': {}", path.display(), e)).into()
+    })
+}
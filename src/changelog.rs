@@ -7,6 +7,8 @@ use std::path::Path;
 
 /// The standard name for the changelog file.
 const CHANGELOG_FILE: &str = "CHANGELOG.md";
+/// The language changelogs are generated in; other languages are translated from it.
+const SOURCE_LANGUAGE: &str = "en";
 /// A standard header for new changelog files.
 const CHANGELOG_HEADER: &str = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\n";
 /// Marker indicating the start of version sections, used for insertion logic.
@@ -33,6 +35,18 @@ pub fn format_changelog_section(version: &str, changes_markdown: &str) -> String
     )
 }
 
+/// Returns whether `language` (a BCP 47 code such as "en" or "en-GB") is the language
+/// changelogs are generated in, so it needs no translation.
+pub fn is_source_language(language: &str) -> bool {
+    let primary = language.split('-').next().unwrap_or(language);
+    primary.eq_ignore_ascii_case(SOURCE_LANGUAGE)
+}
+
+/// Returns the file name of the changelog for `language`, e.g. `CHANGELOG.de.md`.
+pub fn localized_changelog_file(language: &str) -> String {
+    format!("CHANGELOG.{}.md", language)
+}
+
 /// Prepends the newly formatted changelog section to the `CHANGELOG.md` file.
 /// Creates the file with a header if it doesn't exist.
 ///
@@ -40,7 +54,20 @@ pub fn format_changelog_section(version: &str, changes_markdown: &str) -> String
 /// * `project_path` - The root path of the project where `CHANGELOG.md` should reside.
 /// * `new_section` - The fully formatted Markdown section for the new version.
 pub fn write_changelog(project_path: &Path, new_section: &str) -> Result<()> {
-    let changelog_path = project_path.join(CHANGELOG_FILE);
+    write_changelog_file(project_path, Path::new(CHANGELOG_FILE), new_section)
+}
+
+/// Prepends the newly formatted changelog section to the changelog of `language`
+/// (`CHANGELOG.<language>.md`), like `write_changelog`.
+pub fn write_localized_changelog(project_path: &Path, language: &str, new_section: &str) -> Result<()> {
+    write_changelog_file(project_path, Path::new(&localized_changelog_file(language)), new_section)
+}
+
+/// Prepends `new_section` to the changelog-style file `file` (relative to `project_path`),
+/// such as the release notes of a notes profile. Creates the file with a header if it doesn't exist.
+pub fn write_changelog_file(project_path: &Path, file: &Path, new_section: &str) -> Result<()> {
+    let changelog_path = project_path.join(file);
+    let file_name = file.display();
     info!("Updating changelog file: {}", changelog_path.display());
 
     let mut existing_content = String::new();
@@ -50,12 +77,12 @@ pub fn write_changelog(project_path: &Path, new_section: &str) -> Result<()> {
         // Read the existing content if the file exists.
         debug!("Reading existing changelog content.");
         let mut file = File::open(&changelog_path)
-            .with_context(|| format!("Failed to open existing {}", CHANGELOG_FILE))?;
+            .with_context(|| format!("Failed to open existing {}", file_name))?;
         file.read_to_string(&mut existing_content)
-            .with_context(|| format!("Failed to read existing {}", CHANGELOG_FILE))?;
+            .with_context(|| format!("Failed to read existing {}", file_name))?;
     } else {
         // If the file doesn't exist, start with the default header.
-        info!("{} not found. Creating file with default header.", CHANGELOG_FILE);
+        info!("{} not found. Creating file with default header.", file_name);
         existing_content.push_str(CHANGELOG_HEADER);
     }
 
@@ -91,11 +118,11 @@ pub fn write_changelog(project_path: &Path, new_section: &str) -> Result<()> {
         .create(true) // Create if it doesn't exist
         .truncate(true) // Overwrite existing content
         .open(&changelog_path)
-        .with_context(|| format!("Failed to open {} for writing", CHANGELOG_FILE))?;
+        .with_context(|| format!("Failed to open {} for writing", file_name))?;
 
     file.write_all(new_content.as_bytes())
-        .with_context(|| format!("Failed to write updated content to {}", CHANGELOG_FILE))?;
+        .with_context(|| format!("Failed to write updated content to {}", file_name))?;
 
-    info!("Successfully updated {}", CHANGELOG_FILE);
+    info!("Successfully updated {}", file_name);
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+use crate::error::CommitSenseError;
+use crate::git::{self, FileChange};
+use anyhow::{Context, Result};
+use glob::Pattern as GlobPattern;
+use log::{debug, info};
+use std::path::Path;
+
+/// Default total size of the diffs attached to one analysis, in bytes.
+pub const DEFAULT_DIFF_BUDGET: usize = 16_384;
+/// Default size above which a commit's diff is left out, in bytes.
+pub const DEFAULT_MAX_COMMIT_DIFF: usize = 4_096;
+
+/// Lockfiles and generated files that say nothing about the impact of a change.
+/// Matched against the full path and against the file name.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Gemfile.lock",
+    "poetry.lock",
+    "composer.lock",
+    "go.sum",
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+    "*.snap",
+    "*.pb.go",
+    "*_pb2.py",
+    "*.generated.*",
+    "**/generated/**",
+    "dist/**",
+    "target/**",
+];
+
+/// Controls which changes are attached to the commits sent to the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeContextOptions {
+    /// Total size of the diffs attached across all commits, in bytes.
+    pub diff_budget: usize,
+    /// A commit's diff is only attached if it is at most this large, in bytes.
+    pub max_commit_diff: usize,
+    /// Glob patterns of files to leave out, in addition to `DEFAULT_EXCLUDES`.
+    pub exclude: Vec<String>,
+}
+
+impl Default for ChangeContextOptions {
+    fn default() -> Self {
+        ChangeContextOptions {
+            diff_budget: DEFAULT_DIFF_BUDGET,
+            max_commit_diff: DEFAULT_MAX_COMMIT_DIFF,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// What a single commit changed, as shown to the model.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommitChanges {
+    /// Files changed by the commit, without excluded files.
+    pub files: Vec<FileChange>,
+    /// Number of lockfiles and generated files that were left out.
+    pub excluded_files: usize,
+    /// The unified diff of the listed files, if it fit the budget.
+    pub diff: Option<String>,
+}
+
+impl CommitChanges {
+    /// Formats the changes as a block to append to the commit message.
+    pub fn render(&self) -> String {
+        let mut out = String::from("Changed files (+added -deleted):");
+        if self.files.is_empty() {
+            out.push_str("\n  (none besides lockfiles or generated files)");
+        }
+        for file in &self.files {
+            match (file.additions, file.deletions) {
+                (Some(added), Some(deleted)) => {
+                    out.push_str(&format!("\n  {} (+{} -{})", file.path, added, deleted))
+                }
+                _ => out.push_str(&format!("\n  {} (binary)", file.path)),
+            }
+        }
+        if self.excluded_files > 0 {
+            out.push_str(&format!(
+                "\n  ({} lockfile(s) or generated file(s) omitted)",
+                self.excluded_files
+            ));
+        }
+        if let Some(diff) = &self.diff {
+            out.push_str(&format!("\nDiff:\n```diff\n{}\n```", diff.trim_end()));
+        }
+        out
+    }
+}
+
+/// Decides which files are lockfiles or generated files.
+pub struct FileFilter {
+    patterns: Vec<GlobPattern>,
+}
+
+impl FileFilter {
+    /// Builds a filter from the built-in patterns plus `extra` user patterns.
+    pub fn new(extra: &[String]) -> Result<Self> {
+        let patterns = DEFAULT_EXCLUDES
+            .iter()
+            .map(|p| p.to_string())
+            .chain(extra.iter().cloned())
+            .map(|p| {
+                GlobPattern::new(&p).map_err(|e| {
+                    CommitSenseError::Config(format!("Invalid exclude pattern '{}': {}", p, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FileFilter { patterns })
+    }
+
+    /// Returns whether `path` should be left out of the context.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        self.patterns
+            .iter()
+            .any(|p| p.matches(path) || p.matches(file_name))
+    }
+}
+
+/// Collects the changed files of each commit and, while the budget lasts, the diffs of small commits.
+///
+/// The result is in the same order as `oids`.
+pub fn collect(project_path: &Path, oids: &[String], options: &ChangeContextOptions) -> Result<Vec<CommitChanges>> {
+    let filter = FileFilter::new(&options.exclude)?;
+    let mut remaining_budget = options.diff_budget;
+    let mut diffs_attached = 0;
+
+    let mut all_changes = Vec::with_capacity(oids.len());
+    for oid in oids {
+        let files = git::get_commit_numstat(project_path, oid)
+            .with_context(|| format!("Failed to get the changed files of commit {}", oid))?;
+        let (files, excluded): (Vec<FileChange>, Vec<FileChange>) =
+            files.into_iter().partition(|f| !filter.is_excluded(&f.path));
+
+        let mut changes = CommitChanges {
+            excluded_files: excluded.len(),
+            files,
+            diff: None,
+        };
+
+        // Only look at the diff when it can still fit, so huge commits never get loaded
+        let text_files: Vec<String> = changes
+            .files
+            .iter()
+            .filter(|f| f.additions.is_some())
+            .map(|f| f.path.clone())
+            .collect();
+        let changed_lines: u64 = changes
+            .files
+            .iter()
+            .map(|f| f.additions.unwrap_or(0) + f.deletions.unwrap_or(0))
+            .sum();
+        let limit = options.max_commit_diff.min(remaining_budget);
+        if !text_files.is_empty() && (changed_lines as usize) < limit {
+            let diff = git::get_commit_diff(project_path, oid, &text_files)
+                .with_context(|| format!("Failed to get the diff of commit {}", oid))?;
+            if diff.len() <= limit {
+                remaining_budget -= diff.len();
+                diffs_attached += 1;
+                changes.diff = Some(diff);
+            } else {
+                debug!("Diff of commit {} ({} bytes) exceeds the limit of {} bytes.", oid, diff.len(), limit);
+            }
+        }
+        all_changes.push(changes);
+    }
+
+    info!(
+        "Attached changed files for {} commits and diffs for {} of them ({} of {} diff bytes used).",
+        oids.len(),
+        diffs_attached,
+        options.diff_budget - remaining_budget,
+        options.diff_budget
+    );
+    Ok(all_changes)
+}
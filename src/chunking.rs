@@ -0,0 +1,104 @@
+use crate::provider::ChatMessage;
+
+/// Rough number of characters per token for English text and code.
+const CHARS_PER_TOKEN: usize = 4;
+/// Fixed per-message overhead added by chat formatting (role markers, separators).
+const TOKENS_PER_MESSAGE: usize = 4;
+/// Tokens kept free in the context window for the model's answer.
+pub const RESPONSE_RESERVE_TOKENS: usize = 4_096;
+/// Context window assumed for models missing from the built-in table.
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8_192;
+
+/// Known context windows, matched by model name prefix (first match wins, so more specific prefixes come first).
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_000_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("llama3.1", 128_000),
+    ("llama3", 8_192),
+    ("mistral", 32_768),
+    ("qwen2.5", 32_768),
+];
+
+/// Estimates the number of tokens in `text`.
+///
+/// This is a deliberately conservative heuristic (about four characters per token) rather than a
+/// real tokenizer: it only needs to be good enough to decide when a prompt must be split.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Estimates the number of tokens used by a list of chat messages.
+pub fn estimate_message_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| estimate_tokens(&m.content) + TOKENS_PER_MESSAGE)
+        .sum()
+}
+
+/// Returns the context window of `model` from the built-in table.
+pub fn default_context_tokens(model: &str) -> usize {
+    let model = model.to_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_TOKENS, |(_, tokens)| *tokens)
+}
+
+/// Token budgets used to decide whether, and how, to split a commit range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSettings {
+    /// The model's context window in tokens.
+    pub context_tokens: usize,
+    /// The token budget for the commits in each batch of the map step.
+    pub chunk_tokens: usize,
+}
+
+impl ChunkSettings {
+    /// Builds the settings for `model`, applying any configured overrides.
+    /// The chunk budget defaults to a quarter of the context window.
+    pub fn for_model(model: &str, context_tokens: Option<usize>, chunk_tokens: Option<usize>) -> Self {
+        let context_tokens = context_tokens.unwrap_or_else(|| default_context_tokens(model));
+        ChunkSettings {
+            context_tokens,
+            chunk_tokens: chunk_tokens.unwrap_or(context_tokens / 4).max(1),
+        }
+    }
+
+    /// Returns the number of prompt tokens that fit while leaving room for the answer.
+    pub fn prompt_budget(&self) -> usize {
+        self.context_tokens.saturating_sub(RESPONSE_RESERVE_TOKENS)
+    }
+}
+
+/// Splits items into consecutive batches whose estimated token count stays within `budget`.
+///
+/// Order is preserved. An item larger than the budget on its own is placed in a batch by itself.
+pub fn chunk_by_tokens<T, F>(items: &[T], budget: usize, tokens_of: F) -> Vec<&[T]>
+where
+    F: Fn(&T) -> usize,
+{
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+
+    for (i, item) in items.iter().enumerate() {
+        let tokens = tokens_of(item);
+        if i > start && used + tokens > budget {
+            batches.push(&items[start..i]);
+            start = i;
+            used = 0;
+        }
+        used += tokens;
+    }
+    if start < items.len() {
+        batches.push(&items[start..]);
+    }
+    batches
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 use std::str::FromStr; // Required for custom enum parsing with clap v4+
 
@@ -19,23 +19,152 @@ pub struct Cli {
     pub path: PathBuf,
 
     /// Your OpenAI API Key. Can also be set via the OPENAI_API_KEY environment variable.
-    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)] // Required, read from env if not passed
-    pub api_key: String,
+    /// Required for the 'openai' provider; optional for local providers.
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
 
-    /// The base URL for the OpenAI API. Defaults to the official OpenAI endpoint.
+    /// The base URL for the API. Defaults to the official OpenAI endpoint for the 'openai' provider,
+    /// http://localhost:11434 for 'ollama' and http://localhost:8080 for 'llamacpp'. Required for 'azure'
+    /// (the resource endpoint, e.g. https://<resource>.openai.azure.com).
     /// Can also be set via the OPENAI_API_URL environment variable.
-    #[arg(
-        long,
-        env = "OPENAI_API_URL",
-        default_value = "https://api.openai.com/v1"
-    )]
-    pub api_url: String,
+    #[arg(long, env = "OPENAI_API_URL")]
+    pub api_url: Option<String>,
+
+    /// The LLM backend to send requests to ('openai', 'azure', 'ollama' or 'llamacpp').
+    /// 'openai' covers any OpenAI-compatible endpoint; 'azure' needs a deployment in the config file.
+    #[arg(long, env = "COMMITSENSE_PROVIDER", default_value = "openai", value_parser = clap::value_parser!(ProviderKind))]
+    pub provider: ProviderKind,
+
+    /// How the version bump and changelog are determined: 'hybrid' asks the configured provider but
+    /// never accepts a bump lower than Conventional Commits require, 'ai' trusts the provider's bump as-is,
+    /// and 'conventional' applies Conventional Commits rules offline without any API call.
+    /// In 'hybrid' and 'ai' mode, CommitSense falls back to 'conventional' if the API is unreachable.
+    #[arg(long, env = "COMMITSENSE_ANALYZER", default_value = "hybrid", value_parser = clap::value_parser!(Analyzer))]
+    pub analyzer: Analyzer,
 
     /// The specific OpenAI model to use for analysis (e.g., gpt-4o, gpt-3.5-turbo).
     /// Can also be set via the OPENAI_MODEL environment variable.
     #[arg(long, env = "OPENAI_MODEL", default_value = "gpt-4o")]
     pub model: String,
 
+    /// How many times a failed API call is retried on rate limits (429), timeouts,
+    /// server errors (5xx) and connection failures. Retries use exponential backoff with jitter
+    /// and honor the server's `Retry-After` header; a `Retry-After` over 30s fails the call at once.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub max_retries: u32,
+
+    /// The delay before the first retry, in milliseconds. Doubles with every retry (capped at 30s).
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    pub retry_backoff_ms: u64,
+
+    /// Overall time limit for the AI analysis in seconds, including retries, repair turns,
+    /// fallback models, translations and release notes.
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    pub timeout: u64,
+
+    /// How many times the model is asked to correct an answer that fails validation
+    /// (unparsable JSON, invalid bump type or version) before the run fails.
+    #[arg(long, value_name = "N", default_value_t = crate::openai::DEFAULT_REPAIR_ATTEMPTS)]
+    pub repair_attempts: u32,
+
+    /// Number of independent answers to draw from the model. The bump type is decided by majority
+    /// vote, and a close vote is reported as low confidence.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: u32,
+
+    /// Don't ask the provider for schema-constrained (structured) output.
+    /// Use this with OpenAI-compatible gateways that reject the `response_format` parameter.
+    #[arg(long, default_value_t = false)]
+    pub no_structured_output: bool,
+
+    /// Path to the CommitSense configuration file.
+    /// Defaults to `.commitsense/config.toml` in the project directory, which is optional.
+    #[arg(long, value_name = "PATH", env = "COMMITSENSE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Path to a prompt template replacing the built-in prompt.
+    /// Defaults to `.commitsense/prompt.md` in the project directory, which is optional.
+    #[arg(long, value_name = "PATH", env = "COMMITSENSE_PROMPT_TEMPLATE")]
+    pub prompt_template: Option<PathBuf>,
+
+    /// The model's context window in tokens. Overrides the config file and the built-in table.
+    /// Commit ranges whose prompt doesn't fit are summarized in batches first.
+    #[arg(long, value_name = "TOKENS")]
+    pub context_tokens: Option<usize>,
+
+    /// The token budget for the commits in each batch when a range is summarized in batches.
+    /// Overrides the config file. Defaults to a quarter of the context window.
+    #[arg(long, value_name = "TOKENS")]
+    pub chunk_tokens: Option<usize>,
+
+    /// Attach the files each commit changed (`--numstat`) and, within a byte budget, the diffs of
+    /// small commits to the prompt. Lockfiles and generated files are left out.
+    #[arg(long, default_value_t = false)]
+    pub diff_context: bool,
+
+    /// Total size in bytes of the diffs attached with --diff-context.
+    #[arg(long, value_name = "BYTES", default_value_t = crate::changes::DEFAULT_DIFF_BUDGET)]
+    pub diff_budget: usize,
+
+    /// Diffs larger than this many bytes are left out with --diff-context (the file list is still attached).
+    #[arg(long, value_name = "BYTES", default_value_t = crate::changes::DEFAULT_MAX_COMMIT_DIFF)]
+    pub max_commit_diff: usize,
+
+    /// Let the model inspect commits whose message is too terse to judge, by calling tools that show
+    /// a commit, list its changed files or read a file at that commit. Results are redacted.
+    #[arg(long, default_value_t = false)]
+    pub inspect_commits: bool,
+
+    /// Maximum number of tool calls the model may make per run with --inspect-commits.
+    #[arg(long, value_name = "N", default_value_t = crate::tools::DEFAULT_MAX_TOOL_CALLS)]
+    pub max_tool_calls: usize,
+
+    /// Total size in bytes of the tool results sent to the model per run with --inspect-commits.
+    #[arg(long, value_name = "BYTES", default_value_t = crate::tools::DEFAULT_MAX_TOOL_BYTES)]
+    pub max_tool_bytes: usize,
+
+    /// Always call the API instead of reusing a cached suggestion for the same commits.
+    #[arg(long, default_value_t = false)]
+    pub no_cache: bool,
+
+    /// Directory for cached suggestions. Defaults to `.git/commit-sense/cache` in the repository.
+    #[arg(long, value_name = "PATH", env = "COMMITSENSE_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Fail the run (exit code 9) instead of only flagging commits whose text tries to override
+    /// the instructions, the output format or the bump rules.
+    #[arg(long, default_value_t = false)]
+    pub strict_injection: bool,
+
+    /// Write how each commit was classified (type, scope, breaking, user-facing, changelog line)
+    /// to this JSON file, for spot-checking the suggestion.
+    #[arg(long, value_name = "PATH")]
+    pub classifications_out: Option<PathBuf>,
+
+    /// Comma-separated languages (BCP 47 codes, e.g. `en,de,ja`) to write the changelog in.
+    /// Each is written to `CHANGELOG.<lang>.md`; non-English changelogs are translated by the model.
+    #[arg(long, value_name = "LANGS", value_delimiter = ',', value_parser = parse_language)]
+    pub languages: Vec<String>,
+
+    /// Append the run's token usage and estimated cost as a JSON line to this file.
+    #[arg(long, value_name = "PATH", env = "COMMITSENSE_USAGE_LOG")]
+    pub usage_log: Option<PathBuf>,
+
+    /// Append a hash-chained JSON record of the run to this file: the redacted prompts sent to the model,
+    /// its raw responses, the validated suggestion and the corrections applied to it.
+    #[arg(long, value_name = "PATH", env = "COMMITSENSE_AUDIT_LOG")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Save every request sent to the model and its response to this directory, one fixture per
+    /// request keyed by a hash of the normalized request. Bypasses the response cache.
+    #[arg(long, value_name = "DIR", conflicts_with = "llm_replay")]
+    pub llm_record: Option<PathBuf>,
+
+    /// Answer requests from the fixtures recorded with --llm-record in this directory, without any
+    /// network access. A request that wasn't recorded fails the run. Bypasses the response cache.
+    #[arg(long, value_name = "DIR", conflicts_with = "llm_record")]
+    pub llm_replay: Option<PathBuf>,
+
     /// Explicitly specify the project type ('rust' or 'js'/'ts').
     /// If omitted, CommitSense will attempt to auto-detect based on file presence (Cargo.toml or package.json).
     #[arg(long, value_parser = clap::value_parser!(ProjectType))] // Use value_parser for custom enum
@@ -69,6 +198,52 @@ pub struct Cli {
     /// Example: 1.2.3 becomes 1.2.3-nightly.20250410
     #[arg(long, default_value_t = false)]
     pub nightly: bool,
+
+    /// Run another command instead of analyzing the repository. The options above go before it.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands other than the default analysis.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Measure how often the configured analyzer suggests the labelled bump on a dataset of commit sets.
+    Eval(EvalArgs),
+    /// Check that no record of an audit log written with --audit-log was changed, removed or reordered.
+    VerifyAuditLog(VerifyAuditLogArgs),
+}
+
+/// Arguments of `commit-sense verify-audit-log`.
+#[derive(Args, Debug)]
+pub struct VerifyAuditLogArgs {
+    /// The audit log to verify.
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+}
+
+/// Arguments of `commit-sense eval`.
+#[derive(Args, Debug)]
+pub struct EvalArgs {
+    /// JSON Lines file with one labelled case per line: `name`, `commits` (messages), `expected_bump`,
+    /// and optionally `current_version` (default 1.0.0) and `reference_changelog`.
+    #[arg(long, value_name = "PATH")]
+    pub dataset: PathBuf,
+
+    /// Write the full report, including every case, as JSON to this file.
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Fail when the accuracy is below this fraction (0 to 1), e.g. to gate prompt changes in CI.
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fraction)]
+    pub min_accuracy: Option<f64>,
+}
+
+/// Validates a fraction between 0 and 1.
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("Invalid fraction '{}'. Use a number between 0 and 1, e.g. 0.9.", s)),
+    }
 }
 
 /// Enum representing the supported project types for version file handling.
@@ -104,4 +279,118 @@ impl std::fmt::Display for ProjectType {
             ProjectType::JavaScript => write!(f, "JavaScript/TypeScript"),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Enum representing the supported LLM backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    /// OpenAI's Chat Completions API or any compatible gateway.
+    OpenAi,
+    /// A local Ollama server (`/api/chat`).
+    Ollama,
+    /// A local llama.cpp HTTP server (`/completion`).
+    LlamaCpp,
+    /// An Azure OpenAI deployment (`/openai/deployments/<deployment>/chat/completions`).
+    Azure,
+}
+
+impl ProviderKind {
+    /// Returns the base URL used when `--api-url` is not given.
+    /// Azure OpenAI has none: the URL names the customer's resource.
+    pub fn default_api_url(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenAi => Some("https://api.openai.com/v1"),
+            ProviderKind::Ollama => Some("http://localhost:11434"),
+            ProviderKind::LlamaCpp => Some("http://localhost:8080"),
+            ProviderKind::Azure => None,
+        }
+    }
+}
+
+/// Allows clap to parse the provider from a string input.
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "openai" => Ok(ProviderKind::OpenAi),
+            "ollama" => Ok(ProviderKind::Ollama),
+            "llamacpp" | "llama.cpp" | "llama-cpp" => Ok(ProviderKind::LlamaCpp),
+            "azure" | "azure-openai" => Ok(ProviderKind::Azure),
+            _ => Err(format!(
+                "Invalid provider '{}'. Supported providers are 'openai', 'azure', 'ollama', 'llamacpp'.",
+                s
+            )),
+        }
+    }
+}
+
+/// Provides the canonical name of the provider.
+impl std::fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderKind::OpenAi => write!(f, "openai"),
+            ProviderKind::Ollama => write!(f, "ollama"),
+            ProviderKind::LlamaCpp => write!(f, "llamacpp"),
+            ProviderKind::Azure => write!(f, "azure"),
+        }
+    }
+}
+
+/// Enum representing the strategies available for analyzing commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Analyzer {
+    /// Ask the configured LLM provider, using Conventional Commits as a floor for the bump.
+    Hybrid,
+    /// Ask the configured LLM provider and accept its bump as-is.
+    Ai,
+    /// Apply Conventional Commits rules locally, with no network access.
+    Conventional,
+}
+
+/// Allows clap to parse the analyzer from a string input.
+impl FromStr for Analyzer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hybrid" => Ok(Analyzer::Hybrid),
+            "ai" => Ok(Analyzer::Ai),
+            "conventional" | "offline" => Ok(Analyzer::Conventional),
+            _ => Err(format!(
+                "Invalid analyzer '{}'. Supported analyzers are 'hybrid', 'ai', 'conventional'.",
+                s
+            )),
+        }
+    }
+}
+
+/// Provides the canonical name of the analyzer.
+impl std::fmt::Display for Analyzer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Analyzer::Hybrid => write!(f, "hybrid"),
+            Analyzer::Ai => write!(f, "ai"),
+            Analyzer::Conventional => write!(f, "conventional"),
+        }
+    }
+}
+
+/// Validates a language given to `--languages` as a BCP 47 code such as `de`, `ja` or `pt-BR`.
+/// The code ends up in a file name, so anything else is rejected.
+pub fn parse_language(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    let mut subtags = s.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|t| (2..=8).contains(&t.len()) && t.chars().all(|c| c.is_ascii_alphanumeric()));
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "Invalid language '{}'. Use a BCP 47 code such as 'en', 'de', 'ja' or 'pt-BR'.",
+            s
+        ))
+    }
+}
@@ -0,0 +1,254 @@
+use crate::cli::ProviderKind;
+use crate::error::CommitSenseError;
+use anyhow::{Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory (relative to the project path) holding repository-level CommitSense files.
+pub const CONFIG_DIR: &str = ".commitsense";
+/// File name of the optional configuration file inside `CONFIG_DIR`.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Repository-level settings read from `.commitsense/config.toml`.
+///
+/// Every section is optional; a missing file is equivalent to an empty one.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Per-model settings, keyed by the model name passed to `--model`.
+    pub models: HashMap<String, ModelSettings>,
+    /// Models tried in order when `--model` fails, before falling back to the offline rules.
+    pub fallback: Vec<FallbackModel>,
+    /// Settings for the connection to an OpenAI-compatible provider (Azure OpenAI, gateways).
+    pub provider: ProviderSettings,
+    /// Settings for the prompt sent to the model.
+    pub prompt: PromptSettings,
+    /// Settings for the changed files and diffs attached with `--diff-context`.
+    pub diff_context: DiffContextSettings,
+    /// Settings for redacting secrets and personal data before commits are sent to the provider.
+    pub redaction: RedactionSettings,
+    /// Release notes profiles, keyed by name (e.g. `customers`, `developers`).
+    pub notes: BTreeMap<String, NotesProfile>,
+}
+
+/// A model tried when the models before it in the fallback chain fail.
+///
+/// Sampling settings override those in the model's `[models.<name>]` section.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FallbackModel {
+    /// The model to send requests with.
+    pub model: String,
+    /// The provider serving the model ('openai', 'azure', 'ollama' or 'llamacpp'). Defaults to `--provider`.
+    pub provider: Option<String>,
+    /// The base URL of the provider. Defaults to `--api-url` for the same provider, otherwise to the provider's default.
+    pub api_url: Option<String>,
+    /// Environment variable holding the API key. Defaults to `--api-key` for the same provider.
+    pub api_key_env: Option<String>,
+    /// Sampling temperature.
+    pub temperature: Option<f32>,
+    /// Sampling seed, for providers that can sample reproducibly.
+    pub seed: Option<u64>,
+    /// Limit on the tokens generated per request.
+    pub max_tokens: Option<u32>,
+}
+
+impl FallbackModel {
+    /// Returns the provider serving the model, or `default` if none is set.
+    pub fn provider_kind(&self, default: ProviderKind) -> Result<ProviderKind> {
+        match &self.provider {
+            Some(provider) => Ok(provider.parse().map_err(CommitSenseError::Config)?),
+            None => Ok(default),
+        }
+    }
+}
+
+/// Settings for the connection to an OpenAI-compatible provider, such as an Azure OpenAI deployment
+/// or a gateway that needs extra headers.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderSettings {
+    /// The Azure OpenAI deployment requests are sent to (required by the `azure` provider).
+    pub deployment: Option<String>,
+    /// The `api-version` query parameter. Defaults to a GA version for the `azure` provider.
+    pub api_version: Option<String>,
+    /// Header carrying the raw API key instead of `Authorization: Bearer` (e.g. `api-key`).
+    /// Defaults to `api-key` for the `azure` provider.
+    pub auth_header: Option<String>,
+    /// Extra headers sent with every request. `${VAR}` in a value is replaced with the
+    /// environment variable `VAR`, so secrets can stay out of the file.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl ProviderSettings {
+    /// Returns whether any setting is present.
+    pub fn is_empty(&self) -> bool {
+        *self == ProviderSettings::default()
+    }
+
+    /// Returns the extra headers with `${VAR}` references replaced by the environment variables.
+    pub fn resolve_headers(&self) -> Result<Vec<(String, String)>> {
+        self.headers
+            .iter()
+            .map(|(name, value)| {
+                let value = expand_env_vars(value).map_err(|var| {
+                    CommitSenseError::Config(format!(
+                        "Header '{}' uses the environment variable '{}', which is not set",
+                        name, var
+                    ))
+                })?;
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Replaces every `${VAR}` in `value` with the environment variable `VAR`.
+/// Returns the name of the first variable that is not set.
+fn expand_env_vars(value: &str) -> std::result::Result<String, String> {
+    static ENV_VAR: OnceLock<Regex> = OnceLock::new();
+    let regex = ENV_VAR.get_or_init(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("env var pattern is valid"));
+    let mut expanded = String::with_capacity(value.len());
+    let mut last = 0;
+    for captures in regex.captures_iter(value) {
+        let whole = captures.get(0).expect("capture 0 is the match");
+        let name = &captures[1];
+        let var = std::env::var(name).map_err(|_| name.to_string())?;
+        expanded.push_str(&value[last..whole.start()]);
+        expanded.push_str(&var);
+        last = whole.end();
+    }
+    expanded.push_str(&value[last..]);
+    Ok(expanded)
+}
+
+/// Release notes for one audience, generated alongside the changelog.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotesProfile {
+    /// Prompt section describing the audience and the style of the notes.
+    pub instructions: String,
+    /// File the notes are prepended to with `--write`, relative to the project path.
+    /// Defaults to `RELEASE_NOTES.<name>.md`.
+    pub file: Option<PathBuf>,
+}
+
+impl NotesProfile {
+    /// Returns the file the notes of the profile `name` are written to.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("RELEASE_NOTES.{}.md", name)))
+    }
+}
+
+/// Settings for redacting secrets and personal data before commits are sent to the provider.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionSettings {
+    /// Regular expressions to redact in addition to the built-in detectors, keyed by a name used in logs.
+    pub patterns: BTreeMap<String, String>,
+}
+
+/// Settings for the changed files and diffs attached with `--diff-context`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffContextSettings {
+    /// Glob patterns of files to leave out, in addition to the built-in lockfile and generated-file patterns.
+    pub exclude: Vec<String>,
+}
+
+/// Settings for the prompt. The template itself lives in `.commitsense/prompt.md`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptSettings {
+    /// Changelog guidelines substituted for `{{changelog_style}}` in the template.
+    pub changelog_style: Option<String>,
+}
+
+/// Settings that depend on the model being used.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelSettings {
+    /// The model's context window in tokens. Overrides the built-in table.
+    pub context_tokens: Option<usize>,
+    /// The token budget for each batch of commits when a range is too large for one prompt.
+    pub chunk_tokens: Option<usize>,
+    /// Price of a million prompt tokens in USD. Overrides the built-in price table.
+    pub input_cost_per_million: Option<f64>,
+    /// Price of a million completion tokens in USD. Overrides the built-in price table.
+    pub output_cost_per_million: Option<f64>,
+    /// Sampling temperature. The provider's default is used if unset.
+    pub temperature: Option<f32>,
+    /// Sampling seed, for providers that can sample reproducibly.
+    pub seed: Option<u64>,
+    /// Limit on the tokens generated per request.
+    pub max_tokens: Option<u32>,
+}
+
+impl Config {
+    /// Returns the default location of the configuration file for a project.
+    pub fn default_path(project_path: &Path) -> PathBuf {
+        project_path.join(CONFIG_DIR).join(CONFIG_FILE)
+    }
+
+    /// Loads the configuration from `path`.
+    ///
+    /// If `required` is false and the file doesn't exist, an empty configuration is returned.
+    pub fn load(path: &Path, required: bool) -> Result<Self> {
+        if !path.exists() && !required {
+            debug!("No configuration file at '{}'. Using defaults.", path.display());
+            return Ok(Config::default());
+        }
+
+        info!("Loading configuration from '{}'", path.display());
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file '{}'", path.display()))?;
+        let config: Config = toml::from_str(&content).map_err(|e| {
+            CommitSenseError::Config(format!(
+                "Invalid configuration file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        config.validate().map_err(|e| {
+            CommitSenseError::Config(format!("Invalid configuration file '{}': {}", path.display(), e))
+        })?;
+        Ok(config)
+    }
+
+    /// Checks settings that TOML parsing alone can't.
+    fn validate(&self) -> std::result::Result<(), String> {
+        for (i, fallback) in self.fallback.iter().enumerate() {
+            if fallback.model.trim().is_empty() {
+                return Err(format!("fallback model {} has no model name", i + 1));
+            }
+            if let Some(provider) = &fallback.provider {
+                provider.parse::<ProviderKind>()?;
+            }
+        }
+        for (name, profile) in &self.notes {
+            // The name is used in file and GitHub output names
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(format!(
+                    "notes profile name '{}' may only contain letters, digits, '_' and '-'",
+                    name
+                ));
+            }
+            if profile.instructions.trim().is_empty() {
+                return Err(format!("notes profile '{}' has no instructions", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the settings for `model`, or defaults if the model has no section.
+    pub fn model(&self, model: &str) -> ModelSettings {
+        self.models.get(model).cloned().unwrap_or_default()
+    }
+}
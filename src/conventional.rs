@@ -0,0 +1,262 @@
+use crate::git::Commit;
+use crate::openai::{AISuggestion, CommitClassification};
+use crate::version::{self, BumpType};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use semver::Version;
+use std::sync::OnceLock;
+
+/// A commit message parsed according to the Conventional Commits specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// The commit type, lowercased (e.g., "feat", "fix", "docs").
+    pub commit_type: String,
+    /// The optional scope given in parentheses (e.g., "parser" in `fix(parser): ...`).
+    pub scope: Option<String>,
+    /// Whether the commit is marked as breaking via `!` or a `BREAKING CHANGE` footer.
+    pub breaking: bool,
+    /// The description from the header line.
+    pub description: String,
+    /// The text of the `BREAKING CHANGE` footer, if present.
+    pub breaking_note: Option<String>,
+}
+
+/// Matches a Conventional Commits header: `type(scope)!: description`.
+fn header_regex() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| {
+        Regex::new(r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()\r\n]*)\))?(?P<breaking>!)?: (?P<desc>.+)$")
+            .expect("conventional commit header regex is valid")
+    })
+}
+
+/// Matches a `BREAKING CHANGE:` (or `BREAKING-CHANGE:`) footer line.
+fn breaking_footer_regex() -> &'static Regex {
+    static FOOTER: OnceLock<Regex> = OnceLock::new();
+    FOOTER.get_or_init(|| {
+        Regex::new(r"(?m)^BREAKING[ -]CHANGE: ?(?P<note>.*)$")
+            .expect("breaking change footer regex is valid")
+    })
+}
+
+/// Parses a full commit message (subject + body).
+/// Returns `None` if the subject line does not follow the Conventional Commits format.
+pub fn parse_commit(message: &str) -> Option<ConventionalCommit> {
+    let message = message.trim();
+    let header = message.lines().next()?.trim();
+    let caps = header_regex().captures(header)?;
+
+    let body = &message[message.lines().next().map_or(0, str::len)..];
+    let breaking_note = breaking_footer_regex()
+        .captures(body)
+        .map(|c| c["note"].trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    Some(ConventionalCommit {
+        commit_type: caps["type"].to_lowercase(),
+        scope: caps
+            .name("scope")
+            .map(|s| s.as_str().trim().to_string())
+            .filter(|s| !s.is_empty()),
+        breaking: caps.name("breaking").is_some() || breaking_footer_regex().is_match(body),
+        description: caps["desc"].trim().to_string(),
+        breaking_note,
+    })
+}
+
+impl ConventionalCommit {
+    /// Returns the bump this commit requires on its own:
+    /// breaking → major, `feat` → minor, `fix`/`perf` → patch, anything else → none.
+    pub fn bump(&self) -> BumpType {
+        if self.breaking {
+            BumpType::Major
+        } else {
+            match self.commit_type.as_str() {
+                "feat" => BumpType::Minor,
+                "fix" | "perf" => BumpType::Patch,
+                _ => BumpType::None,
+            }
+        }
+    }
+
+    /// Formats the commit as a changelog bullet point.
+    fn changelog_entry(&self) -> String {
+        let description = capitalize(&self.description);
+        match &self.scope {
+            Some(scope) => format!("- **{}:** {}", scope, description),
+            None => format!("- {}", description),
+        }
+    }
+}
+
+/// Computes the highest bump required by any of the commits.
+/// Commits that don't follow the Conventional Commits format contribute nothing.
+pub fn required_bump(commits: &[String]) -> BumpType {
+    commits
+        .iter()
+        .filter_map(|msg| parse_commit(msg))
+        .map(|c| c.bump())
+        .max()
+        .unwrap_or(BumpType::None)
+}
+
+/// Returns the subject lines of the commits that on their own require `bump`.
+/// Used to explain why a minimum bump was enforced.
+pub fn commits_requiring(commits: &[String], bump: BumpType) -> Vec<String> {
+    commits
+        .iter()
+        .filter(|msg| parse_commit(msg).is_some_and(|c| c.bump() == bump))
+        .filter_map(|msg| msg.trim().lines().next().map(String::from))
+        .collect()
+}
+
+/// Classifies a single commit from its Conventional Commits header.
+/// Commits that don't follow the format are classified as "other" and not user-facing.
+pub fn classify(commit: &Commit) -> CommitClassification {
+    let source = "conventional".to_string();
+    match parse_commit(&commit.message) {
+        Some(parsed) => {
+            let user_facing = parsed.bump() > BumpType::None;
+            let changelog_line = match &parsed.breaking_note {
+                Some(note) if parsed.breaking => Some(capitalize(note)),
+                _ if user_facing => Some(parsed.changelog_entry().trim_start_matches("- ").to_string()),
+                _ => None,
+            };
+            CommitClassification {
+                oid: commit.oid.clone(),
+                commit_type: parsed.commit_type,
+                scope: parsed.scope,
+                breaking: parsed.breaking,
+                user_facing,
+                changelog_line,
+                source,
+            }
+        }
+        None => CommitClassification {
+            oid: commit.oid.clone(),
+            commit_type: "other".to_string(),
+            scope: None,
+            breaking: false,
+            user_facing: false,
+            changelog_line: None,
+            source,
+        },
+    }
+}
+
+/// Returns one classification per commit, in commit order: the given (model) classifications where
+/// available, and `classify` for commits they don't cover.
+pub fn fill_classifications(mut classifications: Vec<CommitClassification>, commits: &[Commit]) -> Vec<CommitClassification> {
+    let mut filled = 0;
+    let result = commits
+        .iter()
+        .map(|commit| match classifications.iter().position(|c| c.oid == commit.oid) {
+            Some(index) => classifications.swap_remove(index),
+            None => {
+                filled += 1;
+                classify(commit)
+            }
+        })
+        .collect();
+    if filled > 0 {
+        debug!("Classified {} of {} commits from their Conventional Commits header.", filled, commits.len());
+    }
+    result
+}
+
+/// Produces a version suggestion and changelog from the commits without calling any AI.
+///
+/// The result has the same shape as an AI suggestion, so callers can use either interchangeably.
+///
+/// # Arguments
+/// * `current_version_str` - The current semantic version of the project.
+/// * `commits` - The commit messages since the last release, oldest first.
+pub fn analyze(current_version_str: &str, commits: &[String]) -> Result<AISuggestion> {
+    let current_version = Version::parse(current_version_str).with_context(|| {
+        format!(
+            "Current version '{}' is not a valid semantic version.",
+            current_version_str
+        )
+    })?;
+
+    let parsed: Vec<ConventionalCommit> = commits.iter().filter_map(|msg| parse_commit(msg)).collect();
+    debug!(
+        "{} of {} commits follow the Conventional Commits format.",
+        parsed.len(),
+        commits.len()
+    );
+
+    let bump = parsed.iter().map(|c| c.bump()).max().unwrap_or(BumpType::None);
+    let next_version = version::calculate_expected_version(&current_version, bump.as_str());
+    let changelog_markdown = build_changelog(&parsed);
+    let rationale = match bump {
+        BumpType::None => {
+            "none because no commit is a feature, fix, performance improvement or breaking change.".to_string()
+        }
+        _ => format!(
+            "{} because of: {}",
+            bump,
+            commits_requiring(commits, bump).join("; ")
+        ),
+    };
+
+    info!(
+        "Conventional Commits analysis suggests bump '{}' ({} -> {}).",
+        bump, current_version, next_version
+    );
+    Ok(AISuggestion {
+        bump_type: bump.to_string(),
+        next_version: next_version.to_string(),
+        changelog_markdown,
+        rationale,
+        ..Default::default()
+    })
+}
+
+/// Groups the parsed commits into changelog sections.
+fn build_changelog(commits: &[ConventionalCommit]) -> String {
+    let breaking: Vec<String> = commits
+        .iter()
+        .filter(|c| c.breaking)
+        .map(|c| match &c.breaking_note {
+            Some(note) => format!("- {}", capitalize(note)),
+            None => c.changelog_entry(),
+        })
+        .collect();
+    let section = |types: &[&str]| -> Vec<String> {
+        commits
+            .iter()
+            .filter(|c| types.contains(&c.commit_type.as_str()))
+            .map(ConventionalCommit::changelog_entry)
+            .collect()
+    };
+
+    let sections = [
+        ("Breaking Changes", breaking),
+        ("Features", section(&["feat"])),
+        ("Bug Fixes", section(&["fix"])),
+        ("Performance", section(&["perf"])),
+    ];
+
+    let rendered = sections
+        .iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(title, entries)| format!("### {}\n\n{}", title, entries.join("\n")))
+        .collect::<Vec<String>>();
+
+    if rendered.is_empty() {
+        "No user-facing changes".to_string()
+    } else {
+        rendered.join("\n\n")
+    }
+}
+
+/// Uppercases the first character of a string.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 /// Defines the specific errors that can occur within the CommitSense tool.
@@ -12,8 +13,39 @@ pub enum CommitSenseError {
     #[error("Project file handling error: {0}")]
     Project(String),
 
-    #[error("OpenAI API Error: {0}")]
-    Api(String),
+    // --- API Errors (classified so callers and exit codes can tell them apart) ---
+    #[error("API rate limit exceeded: {message}")]
+    ApiRateLimit {
+        message: String,
+        /// How long the server asked us to wait, from the `Retry-After` header.
+        retry_after: Option<Duration>,
+    },
+
+    #[error("API authentication failed: {0}")]
+    ApiAuth(String),
+
+    #[error("API request timed out: {0}")]
+    ApiTimeout(String),
+
+    #[error("API server error (status {status}): {message}")]
+    ApiServer {
+        status: u16,
+        message: String,
+        /// How long the server asked us to wait, from the `Retry-After` header (e.g. on a 503).
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Could not connect to the API: {0}")]
+    ApiConnection(String),
+
+    #[error("Invalid API response: {0}")]
+    ApiInvalidResponse(String),
+
+    #[error("API request rejected: {0}")] // Other client errors (e.g. 400 Bad Request)
+    ApiRequest(String),
+
+    #[error("Possible prompt injection: {0}")]
+    PromptInjection(String), // A commit tries to steer the model and strict mode is on
 
     #[error("Versioning Error: {0}")]
     Version(String),
@@ -43,4 +75,32 @@ pub enum CommitSenseError {
     Glob(#[from] glob::PatternError),
 
     // ** Git2 variant removed **
-}
\ No newline at end of file
+}
+
+impl CommitSenseError {
+    /// Returns true for transient API failures that are worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            CommitSenseError::ApiRateLimit { .. }
+                | CommitSenseError::ApiTimeout(_)
+                | CommitSenseError::ApiServer { .. }
+                | CommitSenseError::ApiConnection(_)
+        )
+    }
+
+    /// Returns the process exit code used when this error terminates a run.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommitSenseError::Config(_) => 2,
+            CommitSenseError::ApiAuth(_) => 3,
+            CommitSenseError::ApiRateLimit { .. } => 4,
+            CommitSenseError::ApiTimeout(_) => 5,
+            CommitSenseError::ApiServer { .. } | CommitSenseError::ApiConnection(_) => 6,
+            CommitSenseError::ApiInvalidResponse(_) => 7,
+            CommitSenseError::ApiRequest(_) => 8,
+            CommitSenseError::PromptInjection(_) => 9,
+            _ => 1,
+        }
+    }
+}
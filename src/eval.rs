@@ -0,0 +1,276 @@
+use crate::error::CommitSenseError;
+use crate::git::Commit;
+use crate::openai::AISuggestion;
+use crate::version::BumpType;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Bump types from most to least significant, the order of the confusion matrix rows and columns.
+const BUMPS: [BumpType; 4] = [BumpType::Major, BumpType::Minor, BumpType::Patch, BumpType::None];
+
+/// One labelled commit set of an evaluation dataset.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EvalCase {
+    /// A unique name identifying the case in the report.
+    pub name: String,
+    /// The version the commits are released on top of.
+    #[serde(default = "default_current_version")]
+    pub current_version: String,
+    /// The commit messages, oldest first.
+    pub commits: Vec<String>,
+    /// The bump the commits call for.
+    pub expected_bump: BumpType,
+    /// A changelog a maintainer would write for the commits, to compare the generated one with.
+    #[serde(default)]
+    pub reference_changelog: Option<String>,
+}
+
+fn default_current_version() -> String {
+    "1.0.0".to_string()
+}
+
+impl EvalCase {
+    /// Returns the commits of the case. Their IDs are derived from the messages, so the prompts
+    /// (and the fixtures recorded for them) don't change when cases are added or reordered.
+    pub fn commits(&self) -> Vec<Commit> {
+        self.commits
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let digest = Sha256::digest(format!("{}\n{}\n{}", self.name, i, message).as_bytes());
+                let oid = format!("{:x}", digest);
+                Commit::new(&oid[..40], message.clone())
+            })
+            .collect()
+    }
+}
+
+/// Loads an evaluation dataset: a JSON Lines file with one `EvalCase` per line.
+/// Blank lines are skipped. Case names must be unique.
+pub fn load_dataset(path: &Path) -> Result<Vec<EvalCase>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read evaluation dataset '{}'", path.display()))?;
+    let mut cases = Vec::new();
+    let mut names = HashSet::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |reason: String| {
+            CommitSenseError::Config(format!("Invalid evaluation case on line {} of '{}': {}", i + 1, path.display(), reason))
+        };
+        let case: EvalCase = serde_json::from_str(line).map_err(|e| invalid(e.to_string()))?;
+        if case.commits.is_empty() {
+            return Err(invalid(format!("case '{}' has no commits", case.name)).into());
+        }
+        semver::Version::parse(&case.current_version)
+            .map_err(|e| invalid(format!("current_version '{}' is not a semantic version: {}", case.current_version, e)))?;
+        if !names.insert(case.name.clone()) {
+            return Err(invalid(format!("the name '{}' is used by an earlier case", case.name)).into());
+        }
+        cases.push(case);
+    }
+    if cases.is_empty() {
+        return Err(CommitSenseError::Config(format!("The evaluation dataset '{}' has no cases", path.display())).into());
+    }
+    Ok(cases)
+}
+
+/// The outcome of one case.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    /// The name of the case.
+    pub name: String,
+    /// The labelled bump.
+    pub expected: BumpType,
+    /// The suggested bump, or `None` if the analysis failed.
+    pub predicted: Option<BumpType>,
+    /// The model that produced the suggestion (`conventional` when analyzed offline).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Word overlap (F1) of the generated changelog with the reference, if the case has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog_overlap: Option<f64>,
+    /// Why the analysis failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CaseResult {
+    /// Scores the outcome of analyzing `case`.
+    pub fn new(case: &EvalCase, outcome: Result<AISuggestion>) -> Self {
+        let mut result = CaseResult {
+            name: case.name.clone(),
+            expected: case.expected_bump,
+            predicted: None,
+            model: None,
+            changelog_overlap: None,
+            error: None,
+        };
+        match outcome {
+            Ok(suggestion) => match suggestion.bump_type.parse::<BumpType>() {
+                Ok(bump) => {
+                    result.predicted = Some(bump);
+                    result.model = Some(suggestion.model.unwrap_or_else(|| "conventional".to_string()));
+                    result.changelog_overlap = case
+                        .reference_changelog
+                        .as_deref()
+                        .map(|reference| word_overlap(&suggestion.changelog_markdown, reference));
+                }
+                Err(e) => result.error = Some(e),
+            },
+            Err(e) => result.error = Some(format!("{:#}", e)),
+        }
+        result
+    }
+
+    /// Returns whether the suggested bump is the labelled one.
+    pub fn is_correct(&self) -> bool {
+        self.predicted == Some(self.expected)
+    }
+
+    /// Returns whether the suggested bump is lower than the labelled one, which would ship a
+    /// breaking change or feature under too small a version number.
+    pub fn is_under_bump(&self) -> bool {
+        self.predicted.is_some_and(|predicted| predicted < self.expected)
+    }
+}
+
+/// Returns the F1 score of the words shared by `generated` and `reference`, ignoring case,
+/// punctuation and Markdown. Two texts without words score 1.
+pub fn word_overlap(generated: &str, reference: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let generated = words(generated);
+    let reference = words(reference);
+    if generated.is_empty() && reference.is_empty() {
+        return 1.0;
+    }
+    let shared = generated.intersection(&reference).count() as f64;
+    if shared == 0.0 {
+        return 0.0;
+    }
+    let precision = shared / generated.len() as f64;
+    let recall = shared / reference.len() as f64;
+    2.0 * precision * recall / (precision + recall)
+}
+
+/// The results of an evaluation run.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EvalReport {
+    /// Number of cases.
+    pub total: usize,
+    /// Cases whose suggested bump is the labelled one.
+    pub correct: usize,
+    /// `correct / total`. Failed cases count as wrong.
+    pub accuracy: f64,
+    /// Counts of suggested bumps (inner keys) per labelled bump (outer keys). Failed cases are left out.
+    pub confusion_matrix: BTreeMap<BumpType, BTreeMap<BumpType, usize>>,
+    /// Names of the cases whose suggested bump is lower than the labelled one.
+    pub under_bumps: Vec<String>,
+    /// Names of the cases whose suggested bump is higher than the labelled one.
+    pub over_bumps: Vec<String>,
+    /// Names of the cases whose analysis failed.
+    pub failures: Vec<String>,
+    /// Mean word overlap of the generated changelogs with the references, over the cases that have one.
+    pub mean_changelog_overlap: Option<f64>,
+    /// The outcome of every case, in dataset order.
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    /// Aggregates the outcomes of the cases.
+    pub fn new(cases: Vec<CaseResult>) -> Self {
+        let mut confusion_matrix: BTreeMap<BumpType, BTreeMap<BumpType, usize>> = BUMPS
+            .iter()
+            .map(|expected| (*expected, BUMPS.iter().map(|predicted| (*predicted, 0)).collect()))
+            .collect();
+        for case in &cases {
+            if let Some(predicted) = case.predicted {
+                *confusion_matrix
+                    .get_mut(&case.expected)
+                    .and_then(|row| row.get_mut(&predicted))
+                    .expect("every bump pair has a cell") += 1;
+            }
+        }
+        let names = |keep: fn(&CaseResult) -> bool| -> Vec<String> {
+            cases.iter().filter(|c| keep(c)).map(|c| c.name.clone()).collect()
+        };
+        let overlaps: Vec<f64> = cases.iter().filter_map(|c| c.changelog_overlap).collect();
+        let total = cases.len();
+        let correct = cases.iter().filter(|c| c.is_correct()).count();
+        EvalReport {
+            total,
+            correct,
+            accuracy: if total == 0 { 0.0 } else { correct as f64 / total as f64 },
+            confusion_matrix,
+            under_bumps: names(CaseResult::is_under_bump),
+            over_bumps: names(|c| c.predicted.is_some_and(|p| p > c.expected)),
+            failures: names(|c| c.error.is_some()),
+            mean_changelog_overlap: if overlaps.is_empty() {
+                None
+            } else {
+                Some(overlaps.iter().sum::<f64>() / overlaps.len() as f64)
+            },
+            cases,
+        }
+    }
+
+    /// Returns the number of cases labelled `expected` that were suggested `predicted`.
+    pub fn count(&self, expected: BumpType, predicted: BumpType) -> usize {
+        self.confusion_matrix[&expected][&predicted]
+    }
+
+    /// Formats the report for the terminal.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Accuracy: {:.1}% ({}/{} cases)\n\nConfusion matrix (rows: expected, columns: suggested):\n{:<10}",
+            self.accuracy * 100.0,
+            self.correct,
+            self.total,
+            ""
+        );
+        for predicted in BUMPS {
+            out.push_str(&format!("{:>7}", predicted.as_str()));
+        }
+        out.push('\n');
+        for expected in BUMPS {
+            out.push_str(&format!("{:<10}", expected.as_str()));
+            for predicted in BUMPS {
+                out.push_str(&format!("{:>7}", self.count(expected, predicted)));
+            }
+            out.push('\n');
+        }
+
+        let by_name = |name: &String| self.cases.iter().find(|c| &c.name == name).expect("listed cases exist");
+        out.push_str(&format!("\nUnder-bumped: {}\n", self.under_bumps.len()));
+        for case in self.under_bumps.iter().map(by_name) {
+            out.push_str(&format!(
+                "- {}: expected {}, suggested {}\n",
+                case.name,
+                case.expected,
+                case.predicted.expect("under-bumped cases have a suggestion")
+            ));
+        }
+        out.push_str(&format!("Over-bumped: {}\n", self.over_bumps.len()));
+        if !self.failures.is_empty() {
+            out.push_str(&format!("Failed: {}\n", self.failures.len()));
+            for case in self.failures.iter().map(by_name) {
+                out.push_str(&format!("- {}: {}\n", case.name, case.error.as_deref().unwrap_or_default()));
+            }
+        }
+        if let Some(overlap) = self.mean_changelog_overlap {
+            out.push_str(&format!("Changelog word overlap with the references: {:.2}\n", overlap));
+        }
+        out
+    }
+}
@@ -1,15 +1,16 @@
 use crate::error::CommitSenseError;
 use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
 use glob::Pattern as GlobPattern;
 use log::{debug, info, trace, warn};
 use regex::Regex;
 use semver::Version;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
 use std::str;
 
 /// Trait for executing git commands, allowing mocking in tests
-pub trait GitCommandExecutor {
+pub trait GitCommandExecutor: Send + Sync {
     fn run_git_command(&self, path: &Path, args: &[String]) -> Result<String>;
 }
 
@@ -40,13 +41,6 @@ impl GitInterface {
         Self { executor }
     }
 
-    /// Create a new GitInterface with the default executor
-    pub fn default() -> Self {
-        Self {
-            executor: Box::new(DefaultGitCommandExecutor)
-        }
-    }
-
     /// Check if the specified path is a git repository
     pub fn is_git_repo(&self, path: &Path) -> bool {
         let git_dir = path.join(".git");
@@ -59,19 +53,30 @@ impl GitInterface {
         self.executor.run_git_command(path, &args)
     }
 
-    /// Get commits since a specific OID
-    pub fn get_commits_since_oid(&self, path: &Path, base_oid: &str, format: &str) -> Result<Vec<String>> {
-        let range = format!("{}..HEAD", base_oid);
-        let format_arg = format!("--format={}", format);
+    /// Get the commits since a specific OID up to HEAD, oldest first.
+    /// Commits with an empty message are skipped.
+    pub fn get_commits_since_oid(&self, path: &Path, base_oid: &str) -> Result<Vec<Commit>> {
+        info!("Getting commits since base OID: {}", base_oid);
+        let head_oid = self.get_latest_commit_oid(path)?;
+        if head_oid == base_oid {
+            info!("HEAD OID ({}) is the same as the base OID. No new commits.", head_oid);
+            return Ok(Vec::new());
+        }
+
         let args = vec![
             "log".to_string(),
-            range,
-            format_arg,
-            "--reverse".to_string()
+            format!("{}..HEAD", base_oid),
+            "-z".to_string(),
+            COMMIT_LOG_FORMAT.to_string(),
+            "--reverse".to_string(),
         ];
-
         let output = self.executor.run_git_command(path, &args)?;
-        Ok(output.lines().map(String::from).collect())
+        let commits: Vec<Commit> = parse_commit_log(&output)?
+            .into_iter()
+            .filter(|commit| !commit.message.is_empty())
+            .collect();
+        info!("Collected {} commit messages since base commit {}", commits.len(), base_oid);
+        Ok(commits)
     }
 
     /// Find the latest version tag
@@ -92,6 +97,37 @@ impl GitInterface {
 
         Ok(tags[0].to_string())
     }
+
+    /// Show a commit's message, a summary of the changed files and its patch
+    pub fn show_commit(&self, path: &Path, oid: &str) -> Result<String> {
+        let mut args = vec!["show".to_string(), "--no-color".to_string(), "--stat".to_string(), "--patch".to_string()];
+        args.extend(COMMIT_DIFF_ARGS[1..].iter().map(|a| a.to_string()));
+        args.push(oid.to_string());
+        self.executor.run_git_command(path, &args)
+    }
+
+    /// List the files a commit changed with their status letter (A, M, D, ...)
+    pub fn list_changed_files(&self, path: &Path, oid: &str) -> Result<String> {
+        let mut args = vec!["show".to_string(), "--no-color".to_string(), "--name-status".to_string()];
+        args.extend(COMMIT_DIFF_ARGS.iter().map(|a| a.to_string()));
+        args.push(oid.to_string());
+        self.executor.run_git_command(path, &args)
+    }
+
+    /// Read a file as it was at a commit
+    pub fn read_file_at(&self, path: &Path, oid: &str, file: &str) -> Result<String> {
+        let args = vec!["show".to_string(), "--no-color".to_string(), format!("{}:{}", oid, file)];
+        self.executor.run_git_command(path, &args)
+    }
+}
+
+impl Default for GitInterface {
+    /// Create a new GitInterface with the default executor
+    fn default() -> Self {
+        Self {
+            executor: Box::new(DefaultGitCommandExecutor)
+        }
+    }
 }
 
 // --- Helper Function for Running Git Commands (internal) ---
@@ -337,40 +373,220 @@ pub fn find_base_commit_oid(
 }
 
 
-/// Retrieves commit messages (full message) since a given base commit OID, up to HEAD.
+/// Retrieves the commits since a given base commit OID, up to HEAD.
 /// Returns commits in chronological order (oldest relevant commit first).
-pub fn get_commits_since_oid(project_path: &Path, base_oid: &str) -> Result<Vec<String>> {
-    info!("Getting commits since base OID: {}", base_oid);
-
-    // Get HEAD OID to check if base and HEAD are the same
-    let head_oid = get_commit_oid(project_path, "HEAD")?;
-    if head_oid == base_oid {
-         info!("HEAD OID ({}) is the same as the base OID. No new commits.", head_oid);
-         return Ok(Vec::new());
+pub fn get_commits_since_oid(project_path: &Path, base_oid: &str) -> Result<Vec<Commit>> {
+    GitInterface::default().get_commits_since_oid(project_path, base_oid)
+}
+
+/// `git log` format of one commit: hash, parents, author name, author email, author date,
+/// committer date, trailers and raw message, separated by NUL bytes. With `-z`, commits are
+/// separated by NUL bytes too, which can't occur in any of the fields.
+const COMMIT_LOG_FORMAT: &str = "--format=%H%x00%P%x00%an%x00%ae%x00%aI%x00%cI%x00%(trailers:only,unfold)%x00%B";
+
+/// Number of fields of `COMMIT_LOG_FORMAT`.
+const COMMIT_LOG_FIELDS: usize = 8;
+
+/// Parses the output of `git log -z` with `COMMIT_LOG_FORMAT`.
+// `usize::is_multiple_of` would raise the minimum supported Rust version
+#[allow(clippy::manual_is_multiple_of)]
+fn parse_commit_log(output: &str) -> Result<Vec<Commit>, CommitSenseError> {
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut fields: Vec<&str> = output.split('\0').collect();
+    // Every commit ends with a NUL byte, which leaves an empty field at the end
+    if fields.len() % COMMIT_LOG_FIELDS == 1 && fields.last().is_some_and(|f| f.trim().is_empty()) {
+        fields.pop();
+    }
+    if fields.len() % COMMIT_LOG_FIELDS != 0 {
+        return Err(CommitSenseError::GitCommand(format!(
+            "Unexpected git log output: {} fields is not a multiple of {}",
+            fields.len(),
+            COMMIT_LOG_FIELDS
+        )));
+    }
+    fields
+        .chunks(COMMIT_LOG_FIELDS)
+        .map(|f| {
+            Ok(Commit {
+                oid: f[0].trim().to_string(),
+                message: f[7].trim().to_string(),
+                parents: f[1].split_whitespace().map(str::to_string).collect(),
+                author_name: f[2].to_string(),
+                author_email: f[3].to_string(),
+                author_date: parse_commit_date(f[4])?,
+                committer_date: parse_commit_date(f[5])?,
+                trailers: f[6].lines().filter_map(Trailer::parse).collect(),
+                changes: None,
+            })
+        })
+        .collect()
+}
+
+/// Parses a strict ISO 8601 date (`%aI`, `%cI`), or `None` if it is empty.
+fn parse_commit_date(date: &str) -> Result<Option<DateTime<FixedOffset>>, CommitSenseError> {
+    let date = date.trim();
+    if date.is_empty() {
+        return Ok(None);
+    }
+    DateTime::parse_from_rfc3339(date)
+        .map(Some)
+        .map_err(|e| CommitSenseError::GitCommand(format!("Failed to parse commit date '{}': {}", date, e)))
+}
+
+/// Retrieves the OID of the commit HEAD points to.
+pub fn get_head_oid(project_path: &Path) -> Result<String> {
+    Ok(get_commit_oid(project_path, "HEAD")?)
+}
+
+/// A commit in the analyzed range.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Commit {
+    /// The full commit hash.
+    pub oid: String,
+    /// The raw commit message (subject and body, including any trailers).
+    pub message: String,
+    /// The full hashes of the parent commits: none for a root commit, several for a merge.
+    pub parents: Vec<String>,
+    /// The author's name.
+    pub author_name: String,
+    /// The author's email address.
+    pub author_email: String,
+    /// When the change was authored, if known.
+    pub author_date: Option<DateTime<FixedOffset>>,
+    /// When the commit was created, if known.
+    pub committer_date: Option<DateTime<FixedOffset>>,
+    /// The trailers at the end of the message (e.g. `Signed-off-by`), in order.
+    pub trailers: Vec<Trailer>,
+    /// The changed files and diffs attached with `--diff-context`, rendered for the prompt.
+    /// Kept apart from the message, which is all the author wrote.
+    pub changes: Option<String>,
+}
+
+impl Commit {
+    /// Creates a commit from its hash and message, without author, dates, parents or trailers.
+    pub fn new(oid: impl Into<String>, message: impl Into<String>) -> Self {
+        Commit {
+            oid: oid.into(),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the commit merges several parents.
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+
+    /// Returns the values of the trailers named `key` (case-insensitive), in order.
+    pub fn trailer_values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.trailers
+            .iter()
+            .filter(move |t| t.key.eq_ignore_ascii_case(key))
+            .map(|t| t.value.as_str())
+    }
+
+    /// Returns the abbreviated hash used to refer to the commit in prompts and rationales.
+    pub fn short_oid(&self) -> &str {
+        &self.oid[..self.oid.len().min(SHORT_OID_LEN)]
+    }
+
+    /// Returns the text sent to the model: the message, followed by the attached changes if any.
+    pub fn prompt_text(&self) -> String {
+        match &self.changes {
+            Some(changes) => format!("{}\n\n{}", self.message.trim(), changes),
+            None => self.message.trim().to_string(),
+        }
+    }
+
+    /// Returns the first line of the message.
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or("").trim()
+    }
+}
+
+/// A `Key: value` line of the trailer block at the end of a commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trailer {
+    /// The trailer's name, e.g. `Co-authored-by`.
+    pub key: String,
+    /// The trailer's value, unfolded to one line.
+    pub value: String,
+}
+
+impl Trailer {
+    /// Parses a line printed by `%(trailers:only,unfold)`.
+    fn parse(line: &str) -> Option<Self> {
+        let (key, value) = line.split_once(':')?;
+        let key = key.trim();
+        (!key.is_empty()).then(|| Trailer {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        })
     }
+}
+
+/// Length of abbreviated commit hashes.
+pub const SHORT_OID_LEN: usize = 7;
+
+/// Lines added and deleted in one file by a commit, as reported by `git show --numstat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path of the file relative to the repository root.
+    pub path: String,
+    /// Lines added, or `None` for binary files.
+    pub additions: Option<u64>,
+    /// Lines deleted, or `None` for binary files.
+    pub deletions: Option<u64>,
+}
+
+/// Arguments shared by the per-commit diff commands. Merges are diffed against their first parent,
+/// and renames are reported as a deletion plus an addition to keep paths unambiguous.
+const COMMIT_DIFF_ARGS: [&str; 3] = ["--format=", "--no-renames", "--diff-merges=first-parent"];
+
+/// Retrieves the files changed by a commit with their added/deleted line counts.
+pub fn get_commit_numstat(project_path: &Path, oid: &str) -> Result<Vec<FileChange>> {
+    let mut args = vec!["show", "--numstat"];
+    args.extend(COMMIT_DIFF_ARGS);
+    args.push(oid);
+    let output = run_git_command_internal(project_path, &args)?;
+
+    parse_output_lines(output)?
+        .iter()
+        .map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(added), Some(deleted), Some(path)) => Ok(FileChange {
+                    path: path.to_string(),
+                    additions: added.parse().ok(),
+                    deletions: deleted.parse().ok(),
+                }),
+                _ => Err(CommitSenseError::GitCommand(format!(
+                    "Unexpected numstat line for commit {}: '{}'",
+                    oid, line
+                ))
+                .into()),
+            }
+        })
+        .collect()
+}
+
+/// Retrieves the unified diff of a commit, limited to the given paths.
+pub fn get_commit_diff(project_path: &Path, oid: &str, paths: &[String]) -> Result<String> {
+    let mut args = vec!["show", "--patch"];
+    args.extend(COMMIT_DIFF_ARGS);
+    args.push(oid);
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    let output = run_git_command_internal(project_path, &args)?;
+    Ok(parse_output(output)?)
+}
 
-    // Use git log with a specific format to easily parse messages.
-    // %H: commit hash (optional, could use for debugging)
-    // %B: raw body (subject + body)
-    // <EOM>: Custom End-Of-Message marker
-    // --reverse: Output oldest first (chronological)
-    let range = format!("{}..HEAD", base_oid); // Range for log command
-    let format_string = "--format=%B%n<EOM>"; // Message body, newline, and marker
-    let output = run_git_command_internal(project_path, &["log", &range, format_string, "--reverse"])?;
-
-    // Parse the output based on the <EOM> marker
-    let output_str = str::from_utf8(&output.stdout)
-         .map_err(|e| CommitSenseError::GitCommand(format!("Failed to parse git log output: {}", e)))?;
-
-    let commits = output_str
-        .split("\n<EOM>\n") // Split by the marker (with surrounding newlines)
-        .map(|s| s.trim()) // Trim whitespace from each message block
-        .filter(|s| !s.is_empty()) // Filter out empty blocks (e.g., potential trailing split)
-        .map(String::from)
-        .collect::<Vec<String>>();
-
-    info!("Collected {} commit messages since base commit {}", commits.len(), base_oid);
-    Ok(commits)
+/// Returns the absolute path of the repository's `.git` directory (or the worktree's git directory).
+pub fn get_git_dir(project_path: &Path) -> Result<PathBuf> {
+    let output = run_git_command_internal(project_path, &["rev-parse", "--absolute-git-dir"])?;
+    Ok(PathBuf::from(parse_output(output)?))
 }
 
 // --- Legacy Functions ---
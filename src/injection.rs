@@ -0,0 +1,120 @@
+use crate::git::{Commit, SHORT_OID_LEN};
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Longest excerpt of a matched phrase kept in a finding.
+const MAX_PHRASE_CHARS: usize = 80;
+
+/// What a suspicious phrase in a commit tries to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InjectionKind {
+    /// Overrides the instructions or impersonates a chat role ("ignore previous instructions").
+    Instructions,
+    /// Dictates the answer or its format ("respond only with", `"bump": ...`).
+    OutputFormat,
+    /// Dictates the bump type ("output bump major", "this release must be major").
+    BumpRules,
+}
+
+impl fmt::Display for InjectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InjectionKind::Instructions => "instructions",
+            InjectionKind::OutputFormat => "output format",
+            InjectionKind::BumpRules => "bump rules",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A phrase in a commit message that looks like it addresses the model instead of describing a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectionFinding {
+    /// The commit containing the phrase.
+    pub oid: String,
+    /// What the phrase tries to change.
+    pub kind: InjectionKind,
+    /// The matched text, shortened to `MAX_PHRASE_CHARS` characters.
+    pub phrase: String,
+}
+
+impl InjectionFinding {
+    /// Returns the abbreviated hash of the commit.
+    pub fn short_oid(&self) -> &str {
+        &self.oid[..self.oid.len().min(SHORT_OID_LEN)]
+    }
+}
+
+/// Returns the detectors, compiled once. All are case-insensitive.
+fn detectors() -> &'static [(InjectionKind, Regex)] {
+    static DETECTORS: OnceLock<Vec<(InjectionKind, Regex)>> = OnceLock::new();
+    DETECTORS.get_or_init(|| {
+        [
+            (
+                InjectionKind::Instructions,
+                r"\b(ignore|disregard|forget|override)\s+(all\s+|any\s+|the\s+)?(previous|prior|above|earlier|preceding|system|your)\s+(instructions|rules|prompts?|directions|guidelines)",
+            ),
+            (InjectionKind::Instructions, r"\byou\s+are\s+now\b"),
+            (InjectionKind::Instructions, r"\b(new|updated)\s+(system\s+)?instructions\s*:"),
+            (InjectionKind::Instructions, r"(?m)^\s*(system|assistant)\s*:"),
+            (InjectionKind::Instructions, r"<\|im_(start|end)\|>|\[/?INST\]|</?commit\b"),
+            (
+                InjectionKind::OutputFormat,
+                r"\b(respond|reply|answer|output|return|print)\s+(only\s+)?(with\s+|in\s+)?(the\s+following|this|exactly)\s+(json|format|text|answer)",
+            ),
+            (InjectionKind::OutputFormat, r#""(bump|next_version|changelog|rationale|confidence)"\s*:"#),
+            (
+                InjectionKind::OutputFormat,
+                r"\b(do\s+not|don't)\s+(output|return|include|mention)\s+(json|the\s+changelog|this\s+commit)",
+            ),
+            (
+                InjectionKind::BumpRules,
+                r"\b(output|return|set|use|choose|force|select|answer|make)\s+(the\s+|a\s+)?(version\s+)?(bump|release)(\s+type)?\s*(to\s+|as\s+|=|:)?\s*(major|minor|patch|none)\b",
+            ),
+            (
+                InjectionKind::BumpRules,
+                r"\b(bump|release|version)(\s+type)?\s+(must|should|has\s+to|needs\s+to)\s+be\s+(a\s+)?(major|minor|patch|none)\b",
+            ),
+            (
+                InjectionKind::BumpRules,
+                r"\b(treat|classify|consider)\s+(this|these|every|all)\b[^.\n]{0,40}\bas\s+(a\s+)?(major|breaking|minor|patch)\b",
+            ),
+        ]
+        .into_iter()
+        .map(|(kind, pattern)| {
+            let regex = Regex::new(&format!("(?i){}", pattern)).expect("injection pattern is valid");
+            (kind, regex)
+        })
+        .collect()
+    })
+}
+
+/// Finds phrases in `message` that try to steer the model, at most one per kind.
+pub fn scan(message: &str) -> Vec<(InjectionKind, String)> {
+    let mut found: Vec<(InjectionKind, String)> = Vec::new();
+    for (kind, regex) in detectors() {
+        if found.iter().any(|(k, _)| k == kind) {
+            continue;
+        }
+        if let Some(m) = regex.find(message) {
+            let phrase: String = m.as_str().trim().chars().take(MAX_PHRASE_CHARS).collect();
+            found.push((*kind, phrase));
+        }
+    }
+    found
+}
+
+/// Scans every commit message, returning the findings in commit order.
+pub fn scan_commits(commits: &[Commit]) -> Vec<InjectionFinding> {
+    commits
+        .iter()
+        .flat_map(|commit| {
+            scan(&commit.message).into_iter().map(|(kind, phrase)| InjectionFinding {
+                oid: commit.oid.clone(),
+                kind,
+                phrase,
+            })
+        })
+        .collect()
+}
@@ -1,12 +1,27 @@
 //! CommitSense library for analyzing Git commits and generating semantic version bumps
 //! and changelog entries using AI.
 
+pub mod audit;
+pub mod cache;
+pub mod cassette;
 pub mod changelog;
+pub mod changes;
+pub mod chunking;
 pub mod cli;
+pub mod config;
+pub mod conventional;
 pub mod error;
+pub mod eval;
 pub mod git;
+pub mod injection;
 pub mod openai;
 pub mod project;
+pub mod prompt;
+pub mod provider;
+pub mod redact;
+pub mod retry;
+pub mod tools;
+pub mod usage;
 pub mod version;
 
 // Re-export commonly used types
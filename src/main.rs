@@ -1,19 +1,36 @@
-// Declare modules comprising the application
-mod changelog;
-mod cli;
-mod error;
-mod git; // Implementation now uses std::process::Command
-mod openai;
-mod project;
-mod version;
-
 // --- Imports ---
-use crate::{
-    cli::Cli,                // Bring CLI definitions into scope
+// The binary is a thin wrapper around the library crate, so modules are shared rather than redeclared.
+use commit_sense::{
+    audit::{self, AuditTrail, AuditingProvider},
+    cache::{self, CacheKey},
+    cassette::{RecordingProvider, ReplayProvider},
+    changelog,
+    changes,
+    chunking::ChunkSettings,
+    cli::{self, Cli}, // Bring CLI definitions into scope
+    config::Config,
+    conventional,
+    eval,
+    git,
+    injection::{self, InjectionFinding},
+    openai,
+    project,
+    prompt::PromptTemplate,
+    provider,
+    redact::Redactor,
+    retry,
+    tools::{CommitInspector, ToolBudget},
+    usage::{self, ModelPrice, MeteredProvider, RunUsage, UsageMeter},
+    version,
+    CommitSenseError,
+    ProjectType,
 };
 use anyhow::{Context, Result}; // For easy error handling and context addition
 use clap::Parser; // To parse command-line arguments
 use log::{error, info, warn}; // For logging different levels of information
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Entry point of the CommitSense application.
 /// Parses arguments, sets up logging, and orchestrates the main logic.
@@ -22,22 +39,34 @@ async fn main() -> Result<()> {
     // Initialize the logger based on the RUST_LOG environment variable
     // (e.g., RUST_LOG=info, RUST_LOG=commitsense=debug)
     // Defaults to a reasonable level if RUST_LOG is not set.
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .try_init() // Use try_init to avoid panic if logger is already initialized (e.g. in tests)
-        .err() // Ignore the result, logging initialization failure isn't critical enough to stop
-        .map(|e| eprintln!("Warning: Failed to initialize logger: {}", e)); // Print warning to stderr
+    // Use try_init to avoid panic if logger is already initialized (e.g. in tests).
+    // Logging initialization failure isn't critical enough to stop, so only print a warning to stderr.
+    if let Err(e) = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .try_init()
+    {
+        eprintln!("Warning: Failed to initialize logger: {}", e);
+    }
 
     // Parse command-line arguments using the definition in `cli.rs`
     let cli_args = Cli::parse();
     info!("Starting CommitSense v{}...", env!("CARGO_PKG_VERSION"));
 
-    // Execute the core logic, handling potential errors
-    if let Err(e) = run_commitsense(&cli_args).await {
+    // Execute the core logic (or the requested command), handling potential errors
+    let result = match &cli_args.command {
+        Some(cli::Command::Eval(eval_args)) => run_eval(&cli_args, eval_args).await,
+        Some(cli::Command::VerifyAuditLog(args)) => verify_audit_log(args),
+        None => run_commitsense(&cli_args).await,
+    };
+    if let Err(e) = result {
         // Log the error details for debugging
         // Use {:?} for detailed error information, including context chain from anyhow
         error!("CommitSense execution failed: {:?}", e);
-        // Return the error to indicate failure to the OS
-        return Err(e);
+        // Exit with a code that tells callers what kind of failure occurred (see CommitSenseError::exit_code)
+        let exit_code = e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<CommitSenseError>())
+            .map_or(1, CommitSenseError::exit_code);
+        std::process::exit(exit_code);
     }
 
     info!("CommitSense finished successfully.");
@@ -46,6 +75,152 @@ async fn main() -> Result<()> {
 
 // --- Core Logic Function ---
 
+/// Resolves the token budgets for `model`: CLI flags (which describe `--model`) win over the
+/// config file, which wins over the built-in context window table.
+fn chunk_settings(config: &Cli, settings: &Config, model: &str) -> ChunkSettings {
+    let model_settings = settings.model(model);
+    let (context_tokens, chunk_tokens) = if model == config.model {
+        (config.context_tokens, config.chunk_tokens)
+    } else {
+        (None, None)
+    };
+    ChunkSettings::for_model(
+        model,
+        context_tokens.or(model_settings.context_tokens),
+        chunk_tokens.or(model_settings.chunk_tokens),
+    )
+}
+
+/// One model of the fallback chain and where it is served.
+struct ChainModel {
+    provider: cli::ProviderKind,
+    api_url: Option<String>,
+    api_key: Option<String>,
+    model: String,
+    temperature: Option<f32>,
+    seed: Option<u64>,
+    max_tokens: Option<u32>,
+}
+
+/// A client of the fallback chain, with the meter counting its tokens.
+struct ChainLink {
+    provider: cli::ProviderKind,
+    /// Where the model is served (provider, endpoint and deployment), so cached answers of the
+    /// same model name on another backend aren't reused.
+    backend: String,
+    client: openai::OpenAIClient,
+    meter: UsageMeter,
+}
+
+/// Returns the models to try in order: `--model`, then the `[[fallback]]` entries of the config file.
+/// A fallback served by another provider doesn't inherit `--api-url` or `--api-key`.
+fn model_chain(config: &Cli, settings: &Config) -> Result<Vec<ChainModel>> {
+    let primary = settings.model(&config.model);
+    let mut chain = vec![ChainModel {
+        provider: config.provider,
+        api_url: config.api_url.clone(),
+        api_key: config.api_key.clone(),
+        model: config.model.clone(),
+        temperature: primary.temperature,
+        seed: primary.seed,
+        max_tokens: primary.max_tokens,
+    }];
+    for fallback in &settings.fallback {
+        let provider = fallback.provider_kind(config.provider)?;
+        let same_provider = provider == config.provider;
+        let api_key = match &fallback.api_key_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| {
+                CommitSenseError::Config(format!(
+                    "The API key of fallback model '{}' is read from the environment variable '{}', which is not set",
+                    fallback.model, var
+                ))
+            })?),
+            None if same_provider => config.api_key.clone(),
+            None => None,
+        };
+        let model_settings = settings.model(&fallback.model);
+        chain.push(ChainModel {
+            provider,
+            api_url: fallback
+                .api_url
+                .clone()
+                .or_else(|| if same_provider { config.api_url.clone() } else { None }),
+            api_key,
+            model: fallback.model.clone(),
+            temperature: fallback.temperature.or(model_settings.temperature),
+            seed: fallback.seed.or(model_settings.seed),
+            max_tokens: fallback.max_tokens.or(model_settings.max_tokens),
+        });
+    }
+    Ok(chain)
+}
+
+/// Builds a client for every model of the fallback chain, each with its own usage meter.
+/// With --inspect-commits, the models share one inspector of the commits in `project_path`,
+/// redacted by `redactor`, so the tool budget holds for the whole run.
+/// With `audit_trail`, every request of every model is recorded in it.
+fn build_chain(
+    config: &Cli,
+    settings: &Config,
+    prompt_template: &PromptTemplate,
+    project_path: &Path,
+    redactor: &Redactor,
+    audit_trail: Option<&AuditTrail>,
+) -> Result<Vec<ChainLink>> {
+    let inspector = config.inspect_commits.then(|| {
+        let budget = ToolBudget {
+            max_calls: config.max_tool_calls,
+            max_bytes: config.max_tool_bytes,
+        };
+        Arc::new(
+            CommitInspector::new(git::GitInterface::default(), project_path, budget).with_redactor(redactor.clone()),
+        )
+    });
+    model_chain(config, settings)?
+        .iter()
+        .map(|model| {
+            let meter = UsageMeter::default();
+            let client = build_client(config, settings, prompt_template, model, &meter, inspector.as_ref(), audit_trail)?;
+            // The [provider] section describes the connection of --provider only
+            let deployment = settings.provider.deployment.as_deref().filter(|_| model.provider == config.provider);
+            let backend = format!(
+                "{} {} {}",
+                model.provider,
+                model.api_url.as_deref().unwrap_or("default"),
+                deployment.unwrap_or("-")
+            );
+            Ok(ChainLink { provider: model.provider, backend, client, meter })
+        })
+        .collect()
+}
+
+/// Returns the messages of the commits, for the offline analyzer.
+fn commit_messages(commits: &[git::Commit]) -> Vec<String> {
+    commits.iter().map(|c| c.message.clone()).collect()
+}
+
+/// Resolves which changes are attached to the commits with --diff-context.
+fn change_context_options(config: &Cli, settings: &Config) -> changes::ChangeContextOptions {
+    changes::ChangeContextOptions {
+        diff_budget: config.diff_budget,
+        max_commit_diff: config.max_commit_diff,
+        exclude: settings.diff_context.exclude.clone(),
+    }
+}
+
+/// Loads the prompt template (an explicit --prompt-template path must exist) and applies
+/// the configured changelog style.
+fn load_prompt_template(config: &Cli, settings: &Config, project_path: &Path) -> Result<PromptTemplate> {
+    let template = match &config.prompt_template {
+        Some(path) => PromptTemplate::load(path, true)?,
+        None => PromptTemplate::load(&PromptTemplate::default_path(project_path), false)?,
+    };
+    Ok(match &settings.prompt.changelog_style {
+        Some(style) => template.with_changelog_style(style.clone()),
+        None => template,
+    })
+}
+
 /// Orchestrates the main workflow of CommitSense using `std::process::Command` for Git.
 async fn run_commitsense(config: &Cli) -> Result<()> {
     // 1. Resolve Project Path
@@ -58,6 +233,21 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
     })?;
     info!("Operating in target directory: {}", project_path.display());
 
+    // Load the optional repository configuration (an explicit --config path must exist)
+    let settings = match &config.config {
+        Some(path) => Config::load(path, true)?,
+        None => Config::load(&Config::default_path(&project_path), false)?,
+    };
+    // Validate the prompt template up front, so a broken template fails before any git or API work
+    let prompt_template = load_prompt_template(config, &settings, &project_path)?;
+    let redactor = Redactor::new(&settings.redaction.patterns)?;
+    if !config.languages.is_empty() && config.analyzer == cli::Analyzer::Conventional {
+        return Err(CommitSenseError::Config(
+            "--languages needs the 'ai' or 'hybrid' analyzer to translate the changelog.".to_string(),
+        )
+        .into());
+    }
+
     // Note: We no longer open a `git2::Repository` object here.
     // The git functions in `src/git.rs` now take `project_path` as an argument
     // and execute `git` commands within that directory.
@@ -119,30 +309,176 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
         base_oid
     );
 
-    // 5. Interact with OpenAI API
-    // This section remains unchanged as it doesn't depend on the Git implementation details.
-    info!("Initializing OpenAI client...");
-    let openai_client = openai::OpenAIClient::new(
-        config.api_key.clone(), // Clone API key (String)
-        config.api_url.clone(), // Clone API URL (String)
-        config.model.clone(),   // Clone model name (String)
-    );
+    // 5. With --diff-context, attach the files each commit changed for the AI analysis
+    let uses_ai = config.analyzer != cli::Analyzer::Conventional;
+    let commit_oids: Vec<String> = commits.iter().map(|c| c.oid.clone()).collect();
+    let mut commits = if uses_ai && config.diff_context {
+        let all_changes = changes::collect(&project_path, &commit_oids, &change_context_options(config, &settings))?;
+        commits
+            .into_iter()
+            .zip(all_changes)
+            .map(|(commit, changes)| git::Commit {
+                changes: Some(changes.render()),
+                ..commit
+            })
+            .collect()
+    } else {
+        commits
+    };
 
-    // Get the AI's suggestion (includes validation within the method)
-    let ai_suggestion = openai_client
-        .get_version_and_changelog(
-            &current_version_str,
-            &commits,
-            project.project_type(),
-        )
-        .await
-        .context("Failed to get and validate suggestion from OpenAI API")?;
+    // Redact secrets and personal data from everything that is sent to the provider
+    if uses_ai {
+        redactor.redact_commits(&mut commits);
+    }
+
+    // Flag commits whose text tries to steer the model; in strict mode they fail the run
+    let injection_findings = if uses_ai {
+        injection::scan_commits(&commits)
+    } else {
+        Vec::new()
+    };
+    check_injection(&injection_findings, config.strict_injection)?;
+    let flagged_commits = {
+        let mut oids: Vec<&str> = injection_findings.iter().map(|f| f.oid.as_str()).collect();
+        oids.dedup();
+        oids.len()
+    };
+
+    // Open the response cache, so re-runs on the same commits (e.g. dry run, then --write) agree
+    // Recording and replaying need every request to reach the provider
+    let cache = if uses_ai && !config.no_cache && config.llm_record.is_none() && config.llm_replay.is_none() {
+        let dir = match &config.cache_dir {
+            Some(dir) => dir.clone(),
+            None => cache::ResponseCache::default_dir(&git::get_git_dir(&project_path)?),
+        };
+        Some((cache::ResponseCache::new(dir), commit_oids))
+    } else {
+        None
+    };
+
+    // 6. Analyze the commits, either with the chain of LLM models or offline.
+    // Each client's meter counts its tokens, including those of the translations it writes later.
+    // With --audit-log, every request of the run and its response is kept for the audit record
+    let audit_trail = config.audit_log.as_ref().map(|_| AuditTrail::default());
+    let chain = if uses_ai {
+        build_chain(config, &settings, &prompt_template, &project_path, &redactor, audit_trail.as_ref())?
+    } else {
+        Vec::new()
+    };
+    // The time limit covers the whole analysis: every model of the chain, translations and release notes
+    let deadline = analysis_deadline(config);
+    let analysis = analyze_commits(
+        config,
+        &settings,
+        &current_version_str,
+        &commits,
+        project.project_type(),
+        &chain,
+        cache.as_ref(),
+        deadline,
+    )
+    .await;
+    let (ai_suggestion, producer) = match analysis {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            // A failed run is audited too: the prompts were sent all the same
+            if let (Some(path), Some(trail)) = (&config.audit_log, &audit_trail) {
+                if let Err(audit_error) = write_audit_record(path, trail, &project_path, &base_oid, &config.model, Err(&e)) {
+                    warn!("Failed to write the audit record: {:#}", audit_error);
+                }
+            }
+            return Err(e);
+        }
+    };
+    // Follow-up requests go to the model that produced the suggestion
+    let client = producer.or(chain.first()).map(|link| &link.client);
 
     info!(
-        "Received and validated AI suggestion: Bump='{}', NextVersion='{}'",
+        "Received and validated suggestion: Bump='{}', NextVersion='{}'",
         ai_suggestion.bump_type, ai_suggestion.next_version
     );
 
+    // With --languages, translate the changelog; the bump decision above is shared by all languages
+    let publishes = ai_suggestion.bump_type != "none" || config.nightly;
+    let translations = match &client {
+        Some(client) if !config.languages.is_empty() => {
+            if publishes {
+                within_deadline(
+                    config,
+                    deadline,
+                    translate_changelog(client, &config.languages, &ai_suggestion.changelog_markdown),
+                )
+                .await?
+            } else {
+                info!("Bump type is 'none'. Skipping the changelog translations.");
+                Vec::new()
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    // Write the release notes of every notes profile from the same analysis
+    let release_notes = match &client {
+        _ if settings.notes.is_empty() => Vec::new(),
+        Some(client) if publishes => {
+            within_deadline(
+                config,
+                deadline,
+                write_release_notes(
+                    client,
+                    &settings,
+                    &current_version_str,
+                    &ai_suggestion,
+                    &commits,
+                    project.project_type(),
+                ),
+            )
+            .await?
+        }
+        Some(_) => {
+            info!("Bump type is 'none'. Skipping the release notes.");
+            Vec::new()
+        }
+        None => {
+            warn!("Notes profiles need the 'ai' or 'hybrid' analyzer. Skipping the release notes.");
+            Vec::new()
+        }
+    };
+
+    // Account for the tokens spent, so AI spend can be broken down per repository
+    let mut run_usage = RunUsage::default();
+    for link in &chain {
+        run_usage += link.meter.totals();
+    }
+    let estimated_cost = estimate_cost(&settings, &chain);
+    let recorded_link = producer.or(chain.first());
+    if let Some(link) = recorded_link {
+        if let Some(path) = &config.usage_log {
+            let record = usage::UsageRecord {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                repository: repository_name(&project_path),
+                provider: link.provider.to_string(),
+                model: link.client.model().to_string(),
+                usage: run_usage,
+                total_tokens: run_usage.tokens.total(),
+                estimated_cost,
+            };
+            usage::append_usage_log(path, &record)?;
+            info!("Appended token usage to '{}'", path.display());
+        }
+    }
+
+    // Record what was sent to the models and what came back, including the follow-up requests
+    if let (Some(path), Some(trail)) = (&config.audit_log, &audit_trail) {
+        let model = ai_suggestion.model.as_deref().unwrap_or("conventional");
+        write_audit_record(path, trail, &project_path, &base_oid, model, Ok(&ai_suggestion))?;
+    }
+
+    // Write the per-commit classifications, filling in commits the model didn't classify
+    if let Some(path) = &config.classifications_out {
+        write_classifications(path, &ai_suggestion, &commits)?;
+    }
+
     // Apply nightly versioning if requested
     let mut final_version = ai_suggestion.next_version.clone();
     if config.nightly {
@@ -157,16 +493,77 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
         info!("Applied nightly versioning: {} -> {}", ai_suggestion.next_version, final_version);
     }
 
-    // 6. Format the Changelog Section
+    // 7. Format the Changelog Section
     let changelog_section = changelog::format_changelog_section(
         &final_version, // Use the final version (may be nightly)
         &ai_suggestion.changelog_markdown,
     );
+    let localized_sections: Vec<(String, String)> = translations
+        .iter()
+        .map(|(language, markdown)| {
+            (language.clone(), changelog::format_changelog_section(&final_version, markdown))
+        })
+        .collect();
+    let notes_sections: Vec<(String, String)> = release_notes
+        .iter()
+        .map(|(name, notes)| (name.clone(), changelog::format_changelog_section(&final_version, notes)))
+        .collect();
 
-    // 7. Output Results to Console
+    // 8. Output Results to Console
     println!("\n--- CommitSense Analysis ---");
     println!("Suggested Bump Type: {}", ai_suggestion.bump_type);
     println!("Suggested Next Version: {}", ai_suggestion.next_version);
+    if !ai_suggestion.rationale.is_empty() {
+        println!("Rationale: {}", ai_suggestion.rationale);
+    }
+    if let Some(confidence) = ai_suggestion.confidence {
+        println!("Confidence: {:.2}", confidence);
+    }
+    match (&ai_suggestion.model, producer) {
+        (Some(model), Some(link)) if !std::ptr::eq(link, &chain[0]) => println!("Model: {} (fallback)", model),
+        (Some(model), _) => println!("Model: {}", model),
+        (None, _) => println!("Model: none (offline Conventional Commits rules)"),
+    }
+    if let Some(bump_override) = &ai_suggestion.bump_override {
+        println!(
+            "Bump Override: AI suggested '{}', raised to '{}' by commit conventions:",
+            bump_override.ai_bump, bump_override.applied_bump
+        );
+        for commit in &bump_override.commits {
+            println!("  - {}", commit);
+        }
+    }
+    if uses_ai {
+        println!(
+            "Tokens Used: {} (prompt {}, completion {}) in {} request(s){}",
+            run_usage.tokens.total(),
+            run_usage.tokens.prompt_tokens,
+            run_usage.tokens.completion_tokens,
+            run_usage.requests,
+            if run_usage.unreported_requests > 0 { " - some responses carried no token counts" } else { "" }
+        );
+        match estimated_cost {
+            Some(cost) => println!("Estimated Cost: ${:.4}", cost),
+            None => println!(
+                "Estimated Cost: unknown (no price configured for a model used: {})",
+                chain
+                    .iter()
+                    .filter(|link| link.meter.totals().requests > 0)
+                    .map(|link| link.client.model())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        }
+    }
+    if let Some(vote) = &ai_suggestion.vote {
+        println!(
+            "Bump Vote: {} of {} samples agree ({}){}",
+            vote.votes.iter().map(|(_, n)| *n).max().unwrap_or(0),
+            vote.samples,
+            openai::format_votes(vote),
+            if vote.low_confidence { " - LOW CONFIDENCE, please review" } else { "" }
+        );
+    }
     if config.nightly {
         println!("Nightly Version: {}", final_version);
     }
@@ -174,8 +571,20 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
     println!("----------------------------");
     println!("{}", changelog_section);
     println!("----------------------------");
+    for (language, section) in &localized_sections {
+        println!("\nChangelog Section ({}):", language);
+        println!("----------------------------");
+        println!("{}", section);
+        println!("----------------------------");
+    }
+    for (name, section) in &notes_sections {
+        println!("\nRelease Notes ({}):", name);
+        println!("----------------------------");
+        println!("{}", section);
+        println!("----------------------------");
+    }
 
-    // 8. Set Outputs for GitHub Actions
+    // 9. Set Outputs for GitHub Actions
     info!("Setting GitHub Actions outputs...");
     if let Ok(github_output) = std::env::var("GITHUB_OUTPUT") {
         // Using the new environment file approach
@@ -184,6 +593,23 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
         if let Ok(mut file) = OpenOptions::new().append(true).open(github_output) {
             writeln!(file, "bump_type={}", ai_suggestion.bump_type).ok();
             writeln!(file, "next_version={}", ai_suggestion.next_version).ok();
+            writeln!(file, "bump_overridden={}", ai_suggestion.bump_override.is_some()).ok();
+            writeln!(file, "model={}", ai_suggestion.model.as_deref().unwrap_or("conventional")).ok();
+            writeln!(file, "injection_flagged={}", flagged_commits).ok();
+            writeln!(file, "tokens_used={}", run_usage.tokens.total()).ok();
+            if let Some(cost) = estimated_cost {
+                writeln!(file, "estimated_cost={:.6}", cost).ok();
+            }
+            if let Some(bump_override) = &ai_suggestion.bump_override {
+                writeln!(file, "ai_bump_type={}", bump_override.ai_bump).ok();
+            }
+            if let Some(confidence) = ai_suggestion.confidence {
+                writeln!(file, "confidence={:.2}", confidence).ok();
+            }
+            if let Some(vote) = &ai_suggestion.vote {
+                writeln!(file, "vote_agreement={:.2}", vote.agreement).ok();
+                writeln!(file, "low_confidence={}", vote.low_confidence).ok();
+            }
 
             // Add nightly version output if nightly flag is set
             if config.nightly {
@@ -195,19 +621,44 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
             writeln!(file, "changelog<<{}", delimiter).ok();
             writeln!(file, "{}", changelog_section).ok();
             writeln!(file, "{}", delimiter).ok();
+            writeln!(file, "rationale<<{}", delimiter).ok();
+            writeln!(file, "{}", ai_suggestion.rationale).ok();
+            writeln!(file, "{}", delimiter).ok();
+            for (name, section) in &notes_sections {
+                writeln!(file, "notes_{}<<{}", name, delimiter).ok();
+                writeln!(file, "{}", section).ok();
+                writeln!(file, "{}", delimiter).ok();
+            }
         }
     } else {
         // Fallback for local runs or older GitHub Actions
         println!("bump_type: {}", ai_suggestion.bump_type);
         println!("next_version: {}", ai_suggestion.next_version);
+        println!("bump_overridden: {}", ai_suggestion.bump_override.is_some());
+        println!("model: {}", ai_suggestion.model.as_deref().unwrap_or("conventional"));
+        println!("injection_flagged: {}", flagged_commits);
+        println!("tokens_used: {}", run_usage.tokens.total());
+        if let Some(cost) = estimated_cost {
+            println!("estimated_cost: {:.6}", cost);
+        }
+        println!("rationale: {}", ai_suggestion.rationale);
+        if let Some(confidence) = ai_suggestion.confidence {
+            println!("confidence: {:.2}", confidence);
+        }
+        if let Some(vote) = &ai_suggestion.vote {
+            println!("vote_agreement: {:.2}", vote.agreement);
+            println!("low_confidence: {}", vote.low_confidence);
+        }
         if config.nightly {
             println!("nightly_version: {}", final_version);
         }
         println!("\nChangelog:\n{}", changelog_section);
+        for (name, section) in &notes_sections {
+            println!("\nnotes_{}:\n{}", name, section);
+        }
     }
 
-    // 9. Write Changes to Files (if --write flag is enabled)
-    // This section remains unchanged.
+    // 10. Write Changes to Files (if --write flag is enabled)
     if config.write {
         // Only proceed with writing if a version bump actually occurred or nightly is enabled.
         if ai_suggestion.bump_type != "none" || config.nightly {
@@ -223,14 +674,35 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
                 final_version
             );
 
-            // Prepend the generated section to CHANGELOG.md
-            changelog::write_changelog(&project_path, &changelog_section)
-                .context("Failed to update CHANGELOG.md")?;
-            info!("Successfully updated CHANGELOG.md");
+            // Prepend the generated section to CHANGELOG.md, or to CHANGELOG.<lang>.md per language
+            let changelog_files = if localized_sections.is_empty() {
+                changelog::write_changelog(&project_path, &changelog_section)
+                    .context("Failed to update CHANGELOG.md")?;
+                vec!["CHANGELOG.md".to_string()]
+            } else {
+                let mut files = Vec::new();
+                for (language, section) in &localized_sections {
+                    let file = changelog::localized_changelog_file(language);
+                    changelog::write_localized_changelog(&project_path, language, section)
+                        .with_context(|| format!("Failed to update {}", file))?;
+                    files.push(file);
+                }
+                files
+            };
+            // Prepend each notes profile's section to its own file
+            let mut changelog_files = changelog_files;
+            for (name, section) in &notes_sections {
+                let file = settings.notes[name].file(name);
+                changelog::write_changelog_file(&project_path, &file, section)
+                    .with_context(|| format!("Failed to update the '{}' release notes", name))?;
+                changelog_files.push(file.display().to_string());
+            }
+            info!("Successfully updated {}", changelog_files.join(", "));
 
             println!(
-                "\nChanges applied: Project version updated to {} and CHANGELOG.md updated.",
-                final_version
+                "\nChanges applied: Project version updated to {} and {} updated.",
+                final_version,
+                changelog_files.join(", ")
             );
         } else {
             info!("--write flag detected, but bump type is 'none' and nightly is not enabled. No file changes needed.");
@@ -243,4 +715,437 @@ async fn run_commitsense(config: &Cli) -> Result<()> {
     }
 
     Ok(()) // Indicate success
-}
\ No newline at end of file
+}
+
+/// Runs the configured analyzer on every case of an evaluation dataset and reports how often it
+/// suggested the labelled bump. Uses the prompt template and config file of `--path`, but no git history.
+async fn run_eval(config: &Cli, args: &cli::EvalArgs) -> Result<()> {
+    let project_path = config.path.canonicalize().with_context(|| {
+        format!(
+            "Failed to find canonical path for directory '{}'. Does it exist?",
+            config.path.display()
+        )
+    })?;
+    let settings = match &config.config {
+        Some(path) => Config::load(path, true)?,
+        None => Config::load(&Config::default_path(&project_path), false)?,
+    };
+    let prompt_template = load_prompt_template(config, &settings, &project_path)?;
+    let redactor = Redactor::new(&settings.redaction.patterns)?;
+    if config.diff_context || config.inspect_commits {
+        return Err(CommitSenseError::Config(
+            "eval analyzes commit messages only; --diff-context and --inspect-commits need real commits.".to_string(),
+        )
+        .into());
+    }
+    let cases = eval::load_dataset(&args.dataset)?;
+    let project_type = config.project_type.unwrap_or(ProjectType::Rust);
+    info!(
+        "Evaluating the '{}' analyzer on {} case(s) from '{}'...",
+        config.analyzer,
+        cases.len(),
+        args.dataset.display()
+    );
+
+    let uses_ai = config.analyzer != cli::Analyzer::Conventional;
+    let chain = if uses_ai {
+        build_chain(config, &settings, &prompt_template, &project_path, &redactor, None)?
+    } else {
+        Vec::new()
+    };
+    let mut results = Vec::with_capacity(cases.len());
+    for case in &cases {
+        info!("Evaluating case '{}'...", case.name);
+        let mut commits = case.commits();
+        if uses_ai {
+            redactor.redact_commits(&mut commits);
+        }
+        // Cases are independent, so the cache is off: every case must reach the model (or cassette)
+        let deadline = analysis_deadline(config);
+        let outcome = analyze_commits(config, &settings, &case.current_version, &commits, project_type, &chain, None, deadline)
+            .await
+            .map(|(suggestion, _)| suggestion);
+        let outcome = match outcome {
+            // A missing fixture or bad configuration would fail every case, so it stops the evaluation
+            Err(e) if e.chain().any(|cause| matches!(cause.downcast_ref(), Some(CommitSenseError::Config(_)))) => {
+                return Err(e.context(format!("Failed to evaluate case '{}'", case.name)));
+            }
+            Err(e) => {
+                warn!("Case '{}' failed: {:#}", case.name, e);
+                Err(e)
+            }
+            Ok(suggestion) => Ok(suggestion),
+        };
+        results.push(eval::CaseResult::new(case, outcome));
+    }
+
+    let report = eval::EvalReport::new(results);
+    println!("{}", report.render());
+    if let Some(path) = &args.report {
+        let content = serde_json::to_string_pretty(&report).context("Failed to serialize the evaluation report")?;
+        std::fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write the evaluation report to '{}'", path.display()))?;
+        info!("Wrote the evaluation report to '{}'", path.display());
+    }
+    if let Some(min_accuracy) = args.min_accuracy {
+        if report.accuracy < min_accuracy {
+            anyhow::bail!(
+                "Accuracy {:.1}% is below the required {:.1}%",
+                report.accuracy * 100.0,
+                min_accuracy * 100.0
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name of the analyzed repository: `GITHUB_REPOSITORY` in Actions, otherwise the project path.
+fn repository_name(project_path: &Path) -> String {
+    std::env::var("GITHUB_REPOSITORY").unwrap_or_else(|_| project_path.display().to_string())
+}
+
+/// Appends the run's exchanges with the models and its outcome to the audit log at `path`.
+fn write_audit_record(
+    path: &Path,
+    trail: &AuditTrail,
+    project_path: &Path,
+    base_oid: &str,
+    model: &str,
+    outcome: std::result::Result<&openai::AISuggestion, &anyhow::Error>,
+) -> Result<()> {
+    let record = audit::AuditRecord {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        repository: repository_name(project_path),
+        base_oid: base_oid.to_string(),
+        head_oid: git::get_head_oid(project_path)?,
+        model: model.to_string(),
+        exchanges: trail.exchanges(),
+        suggestion: outcome.ok().cloned(),
+        error: outcome.err().map(|e| format!("{:#}", e)),
+        prev_hash: String::new(),
+        hash: String::new(),
+    };
+    let hash = audit::append_audit_record(path, record)?;
+    info!("Appended audit record {} to '{}'", hash, path.display());
+    Ok(())
+}
+
+/// Checks the hash chain of an audit log written with --audit-log.
+fn verify_audit_log(args: &cli::VerifyAuditLogArgs) -> Result<()> {
+    let (records, last_hash) = audit::verify_audit_log(&args.path)?;
+    println!(
+        "Audit log '{}' is intact: {} record(s), last hash {}.",
+        args.path.display(),
+        records,
+        last_hash
+    );
+    Ok(())
+}
+
+/// Estimates the cost of the run in USD from the price of each model used.
+/// Models without requests cost nothing; `None` if a model that was used has no known price.
+fn estimate_cost(settings: &Config, chain: &[ChainLink]) -> Option<f64> {
+    let mut cost = 0.0;
+    for link in chain {
+        let usage = link.meter.totals();
+        if usage.requests == 0 {
+            continue;
+        }
+        let model = link.client.model();
+        let model_settings = settings.model(model);
+        let price = ModelPrice::for_model(
+            model,
+            model_settings.input_cost_per_million,
+            model_settings.output_cost_per_million,
+        )?;
+        cost += price.cost(&usage.tokens);
+    }
+    Some(cost)
+}
+
+/// Logs commits that look like prompt injections. In strict mode, any finding fails the run.
+fn check_injection(findings: &[InjectionFinding], strict: bool) -> Result<()> {
+    for finding in findings {
+        warn!(
+            "Commit {} looks like a prompt injection targeting the {}: \"{}\"",
+            finding.short_oid(),
+            finding.kind,
+            finding.phrase
+        );
+    }
+    if strict && !findings.is_empty() {
+        let commits: Vec<String> = findings
+            .iter()
+            .map(|f| format!("{} ({})", f.short_oid(), f.kind))
+            .collect();
+        return Err(CommitSenseError::PromptInjection(format!(
+            "commits try to override the instructions, output format or bump rules: {}. Reword them or run without --strict-injection.",
+            commits.join(", ")
+        ))
+        .into());
+    }
+    if !findings.is_empty() {
+        warn!("The flagged commits are marked as untrusted in the prompt. Pass --strict-injection to fail instead.");
+    }
+    Ok(())
+}
+
+/// Writes one classification per commit to `path` as a JSON array.
+fn write_classifications(path: &Path, suggestion: &openai::AISuggestion, commits: &[git::Commit]) -> Result<()> {
+    let classifications = conventional::fill_classifications(suggestion.classifications.clone(), commits);
+    let content = serde_json::to_string_pretty(&classifications).context("Failed to serialize classifications")?;
+    std::fs::write(path, content + "\n")
+        .with_context(|| format!("Failed to write classifications to '{}'", path.display()))?;
+    info!("Wrote classifications of {} commits to '{}'", classifications.len(), path.display());
+    Ok(())
+}
+
+/// Builds the client for one model of the fallback chain. Every request it sends is counted by `meter`.
+/// With `inspector`, the model can inspect commits within the inspector's budget.
+/// With `audit_trail`, every request and its response is recorded in it.
+fn build_client(
+    config: &Cli,
+    settings: &Config,
+    prompt_template: &PromptTemplate,
+    model: &ChainModel,
+    meter: &UsageMeter,
+    inspector: Option<&Arc<CommitInspector>>,
+    audit_trail: Option<&AuditTrail>,
+) -> Result<openai::OpenAIClient> {
+    // Prompt building and validation are shared by all providers.
+    // The [provider] section describes the connection of --provider only.
+    info!("Initializing '{}' provider for model '{}'...", model.provider, model.model);
+    let provider_settings = if model.provider == config.provider {
+        settings.provider.clone()
+    } else {
+        Default::default()
+    };
+    let llm_provider: Box<dyn provider::LlmProvider> = match &config.llm_replay {
+        // Replayed runs never touch the network, so no provider or credentials are needed
+        Some(dir) => Box::new(ReplayProvider::new(dir)),
+        None => {
+            let llm_provider = provider::create_provider_with_settings(
+                model.provider,
+                model.api_url.as_deref(),
+                model.api_key.as_deref(),
+                &provider_settings,
+            )?;
+            let retry_policy = retry::RetryPolicy {
+                max_retries: config.max_retries,
+                initial_backoff: Duration::from_millis(config.retry_backoff_ms),
+                ..Default::default()
+            };
+            let llm_provider = Box::new(retry::RetryingProvider::new(llm_provider, retry_policy));
+            match &config.llm_record {
+                Some(dir) => Box::new(RecordingProvider::new(llm_provider, dir)),
+                None => llm_provider,
+            }
+        }
+    };
+    let llm_provider: Box<dyn provider::LlmProvider> = match audit_trail {
+        Some(trail) => Box::new(AuditingProvider::new(llm_provider, trail.clone())),
+        None => llm_provider,
+    };
+    // Count the tokens of every request, including repairs, batches, samples and translations
+    let llm_provider = Box::new(MeteredProvider::new(llm_provider, meter.clone()));
+    let mut client = openai::OpenAIClient::with_provider(llm_provider, model.model.clone())
+        .with_convention_floor(config.analyzer == cli::Analyzer::Hybrid)
+        .with_structured_output(!config.no_structured_output)
+        .with_repair_attempts(config.repair_attempts)
+        .with_chunk_settings(chunk_settings(config, settings, &model.model))
+        .with_prompt_template(prompt_template.clone())
+        .with_samples(config.samples as usize)
+        .with_classifications(config.classifications_out.is_some());
+    if let Some(temperature) = model.temperature {
+        client = client.with_temperature(temperature);
+    }
+    if let Some(seed) = model.seed {
+        client = client.with_seed(seed);
+    }
+    if let Some(max_tokens) = model.max_tokens {
+        client = client.with_max_tokens(max_tokens);
+    }
+    if let Some(inspector) = inspector {
+        client = client.with_commit_inspector(inspector.clone());
+    }
+    Ok(client)
+}
+
+/// Returns the end of the overall time limit (`--timeout`) of an analysis starting now.
+fn analysis_deadline(config: &Cli) -> tokio::time::Instant {
+    tokio::time::Instant::now() + Duration::from_secs(config.timeout)
+}
+
+/// Runs `future` until `deadline`, failing with a timeout error if it doesn't finish by then.
+/// All requests of one analysis share the deadline, so fallbacks and follow-ups can't extend it.
+async fn within_deadline<T>(
+    config: &Cli,
+    deadline: tokio::time::Instant,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout_at(deadline, future).await.unwrap_or_else(|_| {
+        Err(CommitSenseError::ApiTimeout(format!(
+            "AI analysis did not finish within {}s",
+            config.timeout
+        ))
+        .into())
+    })
+}
+
+/// Returns the release notes of every notes profile as `(name, markdown)` pairs, sorted by name.
+async fn write_release_notes(
+    client: &openai::OpenAIClient,
+    settings: &Config,
+    current_version_str: &str,
+    suggestion: &openai::AISuggestion,
+    commits: &[git::Commit],
+    project_type: ProjectType,
+) -> Result<Vec<(String, String)>> {
+    let mut release_notes = Vec::with_capacity(settings.notes.len());
+    for (name, profile) in &settings.notes {
+        info!("Writing the '{}' release notes...", name);
+        let notes = client
+            .write_release_notes(current_version_str, suggestion, commits, project_type, &profile.instructions)
+            .await
+            .with_context(|| format!("Failed to write the '{}' release notes", name))?;
+        release_notes.push((name.clone(), notes));
+    }
+    Ok(release_notes)
+}
+
+/// Returns the changelog in each language as `(language, markdown)` pairs, in the given order.
+/// English is the language of the analysis and reuses its changelog; the others are translated.
+async fn translate_changelog(
+    client: &openai::OpenAIClient,
+    languages: &[String],
+    changelog_markdown: &str,
+) -> Result<Vec<(String, String)>> {
+    let mut translations = Vec::with_capacity(languages.len());
+    for language in languages {
+        let markdown = if changelog::is_source_language(language) {
+            changelog_markdown.to_string()
+        } else {
+            info!("Translating the changelog to '{}'...", language);
+            client
+                .translate_changelog(changelog_markdown, language)
+                .await
+                .with_context(|| format!("Failed to translate the changelog to '{}'", language))?
+        };
+        translations.push((language.clone(), markdown));
+    }
+    Ok(translations)
+}
+
+/// Produces the version suggestion with the first model of the chain that succeeds, or offline
+/// when there is no chain. Returns the suggestion and the link that produced it (`None` if offline).
+///
+/// With fallback models, any API failure (including answers that stay invalid after repair attempts)
+/// moves on to the next model, and the last failure falls back to offline Conventional Commits
+/// analysis. With a single model, only an unreachable API falls back offline. All models share
+/// `deadline`; once it has passed, the remaining ones are skipped.
+#[allow(clippy::too_many_arguments)]
+async fn analyze_commits<'a>(
+    config: &Cli,
+    settings: &Config,
+    current_version_str: &str,
+    commits: &[git::Commit],
+    project_type: ProjectType,
+    chain: &'a [ChainLink],
+    cache: Option<&(cache::ResponseCache, Vec<String>)>,
+    deadline: tokio::time::Instant,
+) -> Result<(openai::AISuggestion, Option<&'a ChainLink>)> {
+    let Some(primary) = chain.first() else {
+        info!("Analyzing commits offline using Conventional Commits rules...");
+        return Ok((conventional::analyze(current_version_str, &commit_messages(commits))?, None));
+    };
+
+    // Reuse the suggestion from an earlier run on the same commits, if any
+    let cache_entry = cache.map(|(cache, commit_oids)| {
+        // The attached diff context changes the prompt without changing the OIDs
+        let mut prompt_template = primary.client.prompt_template(project_type);
+        if config.diff_context {
+            prompt_template.push_str(&format!(
+                "\n\n[diff_context]\n{:?}",
+                change_context_options(config, settings)
+            ));
+        }
+        // So do user redaction patterns, which change what the model sees of the commits
+        if !settings.redaction.patterns.is_empty() {
+            prompt_template.push_str(&format!("\n\n[redaction]\n{:?}", settings.redaction.patterns));
+        }
+        let key = CacheKey::new(
+            &primary.backend,
+            primary.client.model(),
+            &prompt_template,
+            current_version_str,
+            commit_oids,
+        );
+        (cache, key)
+    });
+    if let Some((cache, key)) = &cache_entry {
+        if let Some(suggestion) = cache.get(key) {
+            info!(
+                "Using cached suggestion from '{}' (pass --no-cache to call the API again).",
+                cache.dir().display()
+            );
+            return Ok((suggestion, Some(primary)));
+        }
+    }
+
+    let has_fallbacks = chain.len() > 1;
+    let mut last_error = None;
+    for (i, link) in chain.iter().enumerate() {
+        if i > 0 && tokio::time::Instant::now() >= deadline {
+            warn!(
+                "The time limit of {}s is used up. Skipping the remaining {} model(s) of the chain.",
+                config.timeout,
+                chain.len() - i
+            );
+            break;
+        }
+        if i > 0 {
+            warn!(
+                "Falling back to model '{}' ({}/{} in the chain).",
+                link.client.model(),
+                i + 1,
+                chain.len()
+            );
+        }
+
+        // Get the AI's suggestion (includes validation within the method),
+        // bounded by what is left of the overall time limit
+        let result = within_deadline(
+            config,
+            deadline,
+            link.client.get_version_and_changelog_for_commits(current_version_str, commits, project_type),
+        )
+        .await;
+
+        match result {
+            Ok(suggestion) => {
+                // Only the primary model's answers are cached, so the next run asks it again
+                if i == 0 {
+                    if let Some((cache, key)) = &cache_entry {
+                        if let Err(e) = cache.put(key, &suggestion) {
+                            warn!("Failed to cache the suggestion: {:#}", e);
+                        }
+                    }
+                }
+                return Ok((suggestion, Some(link)));
+            }
+            // Keep releases flowing during provider outages and, with a chain, bad answers
+            Err(e) if provider::is_unreachable(&e) || (has_fallbacks && provider::is_api_error(&e)) => {
+                warn!("Model '{}' failed: {:#}", link.client.model(), e);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e.context("Failed to get and validate suggestion from the API")),
+        }
+    }
+
+    let e = last_error.expect("every model of the chain failed");
+    warn!(
+        "No model of the chain produced a suggestion (last error: {:#}). Falling back to offline Conventional Commits analysis.",
+        e
+    );
+    Ok((conventional::analyze(current_version_str, &commit_messages(commits))?, None))
+}
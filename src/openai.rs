@@ -1,45 +1,25 @@
+use crate::chunking::{self, ChunkSettings};
 use crate::cli::ProjectType;
+use crate::conventional;
 use crate::error::CommitSenseError;
-use crate::version;
+use crate::git::Commit;
+use crate::injection;
+use crate::prompt::{PromptTemplate, UNTRUSTED_DATA_NOTICE};
+use crate::provider::{
+    ChatMessage, CompletionRequest, JsonSchema, LlmProvider, OpenAiCompatibleProvider,
+};
+use crate::tools::{CommitInspector, ToolCall};
+use crate::version::{self, BumpType};
 use anyhow::{Context, Result};
 use log::{debug, info, warn};
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use openai_api_rs::v1::{api::OpenAIClient as OpenAIApiClient, chat_completion};
-
-// --- Structures for OpenAI API Interaction ---
-
-/// Represents the request body for the Chat Completions API.
-#[derive(Serialize, Debug)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<ChatMessage>,
-    temperature: Option<f32>,
-}
-
-/// Represents a single message in the chat conversation.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct ChatMessage {
-    role: String,
-    content: String,
-}
-
-/// Represents the overall structure of the response from the Chat Completions API.
-#[derive(Deserialize, Debug)]
-struct ChatCompletionResponse {
-    choices: Vec<ChatCompletionChoice>,
-}
-
-/// Represents a single completion choice provided by the model.
-#[derive(Deserialize, Debug)]
-struct ChatCompletionChoice {
-    message: ChatMessage,
-}
+use std::sync::Arc;
 
 // --- Structures for CommitSense Logic ---
 
 /// Defines the structure of the JSON object expected in the AI's response content.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct AISuggestion {
     /// The type of version bump suggested ("major", "minor", "patch", "none").
     #[serde(rename = "bump")]
@@ -49,70 +29,714 @@ pub struct AISuggestion {
     /// The Markdown formatted changelog points generated by the AI.
     #[serde(rename = "changelog")]
     pub changelog_markdown: String,
+    /// Why this bump was chosen, citing the commit(s) that drove the decision.
+    #[serde(default)]
+    pub rationale: String,
+    /// How sure the model is about the bump type, from 0.0 to 1.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Set when the hybrid policy raised the AI's bump to the floor required by commit conventions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump_override: Option<BumpOverride>,
+    /// Set when several samples were drawn and the bump type was decided by majority vote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vote: Option<BumpVote>,
+    /// Per-commit classifications, if they were requested (see `OpenAIClient::with_classifications`).
+    #[serde(default, rename = "commits", skip_serializing_if = "Vec::is_empty")]
+    pub classifications: Vec<CommitClassification>,
+    /// The model that produced the suggestion (filled in locally; `None` for the offline analyzer).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Changes CommitSense made to the model's answer, in the order they were applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub corrections: Vec<Correction>,
+}
+
+/// How a single commit was classified, so the aggregate suggestion can be spot-checked.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CommitClassification {
+    /// The full commit hash. The model answers with the abbreviated hash, which is resolved locally.
+    #[serde(alias = "id")]
+    pub oid: String,
+    /// The inferred commit type (e.g. "feat", "fix", "docs", "other").
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    /// The inferred scope, if any.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Whether the commit breaks backwards compatibility.
+    #[serde(default)]
+    pub breaking: bool,
+    /// Whether users of the project would notice the change.
+    #[serde(default)]
+    pub user_facing: bool,
+    /// The changelog entry the commit produced, if any.
+    #[serde(default)]
+    pub changelog_line: Option<String>,
+    /// Who classified the commit: "model", or "conventional" for the Conventional Commits parser.
+    #[serde(default = "model_source")]
+    pub source: String,
+}
+
+fn model_source() -> String {
+    "model".to_string()
+}
+
+/// Instructions appended to the system message when per-commit classifications are requested.
+const CLASSIFICATION_FORMAT: &str = "Also classify every commit: add a \"commits\" array to the JSON object with one entry per commit, in the order given:\n```json\n\"commits\": [\n  {\"id\": \"abc1234\", \"type\": \"feat\", \"scope\": \"cli\", \"breaking\": false, \"user_facing\": true, \"changelog_line\": \"Add --verbose flag\"}\n]\n```\nUse the commit ID exactly as given. \"type\" is one of feat, fix, perf, refactor, docs, test, build, ci, chore, style, revert or other. \"scope\" and \"changelog_line\" are null if the commit has no scope or produced no changelog entry.";
+
+/// Returns the JSON schema of the per-commit classification array.
+fn classifications_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "type": { "type": "string" },
+                "scope": { "type": ["string", "null"] },
+                "breaking": { "type": "boolean" },
+                "user_facing": { "type": "boolean" },
+                "changelog_line": { "type": ["string", "null"] }
+            },
+            "required": ["id", "type", "scope", "breaking", "user_facing", "changelog_line"],
+            "additionalProperties": false
+        }
+    })
+}
+
+/// Extends an object schema with the required "commits" classification array.
+fn with_classifications_schema(mut schema: JsonSchema) -> JsonSchema {
+    schema.schema["properties"]["commits"] = classifications_schema();
+    if let Some(required) = schema.schema["required"].as_array_mut() {
+        required.push(serde_json::json!("commits"));
+    }
+    schema
+}
+
+/// Records that the AI's bump was raised to satisfy the Conventional Commits floor.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BumpOverride {
+    /// The bump type originally returned by the AI.
+    pub ai_bump: String,
+    /// The bump type that was applied instead.
+    pub applied_bump: String,
+    /// The subject lines of the commits that required the higher bump.
+    pub commits: Vec<String>,
+}
+
+/// A field of the model's answer that CommitSense replaced, and why.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Correction {
+    /// The corrected field ("bump" or "next_version").
+    pub field: String,
+    /// The value the model answered with.
+    pub from: String,
+    /// The value that was applied instead.
+    pub to: String,
+    /// Why the value was replaced.
+    pub reason: String,
+}
+
+impl Correction {
+    fn new(field: &str, from: impl ToString, to: impl ToString, reason: String) -> Self {
+        Correction {
+            field: field.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            reason,
+        }
+    }
+}
+
+/// The outcome of the majority vote on the bump type across several samples.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BumpVote {
+    /// Number of valid samples that took part in the vote.
+    pub samples: usize,
+    /// Votes per bump type, highest bump first.
+    pub votes: Vec<(String, usize)>,
+    /// Share of the samples that voted for the winning bump type (0.0 to 1.0).
+    pub agreement: f64,
+    /// Whether the vote was too close to trust without a human look.
+    pub low_confidence: bool,
+}
+
+/// Agreement below which a vote is reported as low confidence (e.g. 2 of 3 samples).
+pub const LOW_CONFIDENCE_AGREEMENT: f64 = 0.75;
+
+/// Default number of follow-up turns used to repair an invalid AI answer.
+pub const DEFAULT_REPAIR_ATTEMPTS: u32 = 2;
+
+impl AISuggestion {
+    /// Returns the JSON schema of the object the model is asked to produce.
+    pub fn json_schema() -> JsonSchema {
+        JsonSchema {
+            name: "version_suggestion".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "bump": { "type": "string", "enum": ["major", "minor", "patch", "none"] },
+                    "next_version": { "type": "string" },
+                    "changelog": { "type": "string" },
+                    "rationale": { "type": "string" },
+                    "confidence": { "type": "number", "minimum": 0, "maximum": 1 }
+                },
+                "required": ["bump", "next_version", "changelog", "rationale", "confidence"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+/// The classification and summary of one batch of commits, produced by the map step for large ranges.
+#[derive(Deserialize, Debug, Clone)]
+struct BatchSummary {
+    /// The highest bump required by any commit in the batch.
+    bump: String,
+    /// Markdown bullet points describing the user-facing changes in the batch.
+    summary: String,
+    /// Per-commit classifications of the batch, if they were requested.
+    #[serde(default)]
+    commits: Vec<CommitClassification>,
+    /// Number of commits in the batch (filled in locally, not by the model).
+    #[serde(skip)]
+    commit_count: usize,
+}
+
+impl BatchSummary {
+    /// Returns the JSON schema of the object the model is asked to produce in the map step.
+    fn json_schema() -> JsonSchema {
+        JsonSchema {
+            name: "batch_summary".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "bump": { "type": "string", "enum": ["major", "minor", "patch", "none"] },
+                    "summary": { "type": "string" }
+                },
+                "required": ["bump", "summary"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+/// A reply asking to run a tool instead of answering.
+#[derive(Deserialize, Debug)]
+struct ToolCallReply {
+    tool_call: ToolCall,
+}
+
+/// A translated changelog, as returned by the model.
+#[derive(Deserialize, Debug)]
+struct Translation {
+    changelog: String,
+}
+
+impl Translation {
+    /// Returns the JSON schema of the object the model is asked to produce when translating.
+    fn json_schema() -> JsonSchema {
+        JsonSchema {
+            name: "translation".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "changelog": { "type": "string" }
+                },
+                "required": ["changelog"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+/// Release notes for one audience, as returned by the model.
+#[derive(Deserialize, Debug)]
+struct ReleaseNotes {
+    notes: String,
+}
+
+impl ReleaseNotes {
+    /// Returns the JSON schema of the object the model is asked to produce for a notes profile.
+    fn json_schema() -> JsonSchema {
+        JsonSchema {
+            name: "release_notes".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "notes": { "type": "string" }
+                },
+                "required": ["notes"],
+                "additionalProperties": false
+            }),
+        }
+    }
 }
 
-/// Client for interacting with the OpenAI API.
+/// Client for generating version suggestions through an `LlmProvider`.
+/// Despite the name, any backend implementing the trait can be plugged in.
 pub struct OpenAIClient {
-    api_key: String,
-    api_url: String,
+    provider: Box<dyn LlmProvider>,
     model: String,
+    /// Whether Conventional Commits set a minimum bump the AI cannot go below (hybrid policy).
+    convention_floor: bool,
+    /// Whether to request schema-constrained output from providers that support it.
+    structured_output: bool,
+    /// How many follow-up turns may be spent asking the model to fix an invalid answer.
+    repair_attempts: u32,
+    /// Token budgets deciding when commits are summarized in batches first.
+    chunk_settings: ChunkSettings,
+    /// The prompt sent to the model.
+    prompt: PromptTemplate,
+    /// How many independent answers are drawn and voted on.
+    samples: usize,
+    /// Whether the model is asked to classify every commit as well.
+    classify: bool,
+    /// Runs the tool calls the model makes to inspect commits, if tools are offered.
+    inspector: Option<Arc<CommitInspector>>,
+    /// Sampling temperature sent with every request, if set.
+    temperature: Option<f32>,
+    /// Sampling seed sent with every request, if set.
+    seed: Option<u64>,
+    /// Limit on the tokens generated per request, if set.
+    max_tokens: Option<u32>,
 }
 
 impl OpenAIClient {
-    /// Creates a new OpenAIClient instance.
+    /// Creates a new OpenAIClient talking to an OpenAI-compatible endpoint at `api_url`.
     pub fn new(api_key: String, api_url: String, model: String) -> Self {
+        Self::with_provider(
+            Box::new(OpenAiCompatibleProvider::new(api_url, api_key)),
+            model,
+        )
+    }
+
+    /// Creates a new OpenAIClient backed by an arbitrary provider.
+    pub fn with_provider(provider: Box<dyn LlmProvider>, model: String) -> Self {
         OpenAIClient {
-            api_key,
-            api_url,
+            chunk_settings: ChunkSettings::for_model(&model, None, None),
+            provider,
             model,
+            convention_floor: false,
+            structured_output: true,
+            repair_attempts: DEFAULT_REPAIR_ATTEMPTS,
+            prompt: PromptTemplate::default(),
+            samples: 1,
+            classify: false,
+            inspector: None,
+            temperature: None,
+            seed: None,
+            max_tokens: None,
         }
     }
 
+    /// Enables or disables the provider's structured-output mode (e.g. `response_format` JSON schema).
+    /// Disable this for gateways that reject the parameter.
+    pub fn with_structured_output(mut self, enabled: bool) -> Self {
+        self.structured_output = enabled;
+        self
+    }
+
+    /// Sets how many times the model is asked to correct an answer that fails validation.
+    pub fn with_repair_attempts(mut self, attempts: u32) -> Self {
+        self.repair_attempts = attempts;
+        self
+    }
+
+    /// Enables or disables the hybrid policy: a deterministic Conventional Commits pass computes
+    /// the minimum bump, and the AI may raise it but never lower it.
+    pub fn with_convention_floor(mut self, enabled: bool) -> Self {
+        self.convention_floor = enabled;
+        self
+    }
+
+    /// Overrides the token budgets used to decide when to summarize commits in batches.
+    pub fn with_chunk_settings(mut self, settings: ChunkSettings) -> Self {
+        self.chunk_settings = settings;
+        self
+    }
+
+    /// Replaces the built-in prompt with a template (e.g. loaded from `.commitsense/prompt.md`).
+    pub fn with_prompt_template(mut self, prompt: PromptTemplate) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Sets how many independent answers are drawn for the final step. The bump type is decided
+    /// by majority vote, and the changelog is taken from a sample that voted for the winner.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Asks the model to classify every commit (type, scope, breaking, user-facing, changelog line)
+    /// alongside the suggestion. Only commits with known hashes can be classified.
+    pub fn with_classifications(mut self, enabled: bool) -> Self {
+        self.classify = enabled;
+        self
+    }
+
+    /// Lets the model call tools (`show_commit`, `list_changed_files`, `read_file_at`) to look at the
+    /// actual change of a commit before deciding the bump, within the inspector's budget.
+    /// Clients sharing an inspector share its budget.
+    pub fn with_commit_inspector(mut self, inspector: Arc<CommitInspector>) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
+
+    /// Sets the sampling temperature sent with every request (the provider's default otherwise).
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Sets the seed sent with every request, for providers that can sample reproducibly.
+    /// With `--samples`, sample `n` (from 0) is drawn with `seed + n` so the samples can differ.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Limits the number of tokens the model may generate per request.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Returns the model name requests are sent with.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the prompt with placeholders instead of the version and commits, together with the
+    /// settings that change the validated result. Used to tell cached suggestions apart.
+    pub fn prompt_template(&self, project_type: ProjectType) -> String {
+        let messages = self.prompt.render("{current_version}", project_type, "{commits}");
+        let mut template = messages
+            .iter()
+            .map(|m| format!("[{}]\n{}", m.role, m.content))
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        template.push_str(&format!(
+            "\n\n[policy]\nconvention_floor={}\nsamples={}\nclassify={}\ninspect_commits={}\ntemperature={:?}\nseed={:?}\nmax_tokens={:?}",
+            self.convention_floor,
+            self.samples,
+            self.classify,
+            self.inspector.is_some(),
+            self.temperature,
+            self.seed,
+            self.max_tokens
+        ));
+        template
+    }
+
     /// Constructs the prompt messages (system and user) for the OpenAI API call.
     fn build_prompt(
         &self,
         current_version: &str,
-        commits: &[String],
+        commits: &[Commit],
+        project_type: ProjectType,
+        classify: bool,
+    ) -> Vec<ChatMessage> {
+        let mut messages = self
+            .prompt
+            .render(current_version, project_type, &format_commit_list(commits));
+        if classify {
+            messages[0].content.push_str("\n\n");
+            messages[0].content.push_str(CLASSIFICATION_FORMAT);
+        }
+        if let Some(inspector) = &self.inspector {
+            messages[0].content.push_str("\n\n");
+            messages[0].content.push_str(&inspector.instructions());
+        }
+        messages
+    }
+
+    /// Constructs the final (reduce) prompt from batch summaries when the commits were too many for one request.
+    fn build_reduce_prompt(
+        &self,
+        current_version: &str,
+        summaries: &[BatchSummary],
+        total_commits: usize,
         project_type: ProjectType,
     ) -> Vec<ChatMessage> {
-        // Format the list of commits for clarity in the prompt
-        let commit_list = commits
+        let summary_list = summaries
             .iter()
-            .map(|msg| format!("---\n{}\n---", msg.trim()))
+            .enumerate()
+            .map(|(i, s)| {
+                format!(
+                    "--- Batch {} ({} commits, highest bump: {}) ---\n{}",
+                    i + 1,
+                    s.commit_count,
+                    s.bump,
+                    s.summary.trim()
+                )
+            })
             .collect::<Vec<String>>()
             .join("\n\n");
 
-        // Build parts of the system prompt separately to avoid format string issues
-        let intro = format!("You are an AI assistant specializing in software versioning and release notes. Your task is to analyze the following git commit messages since the last release (currently version {}) for a {} project.",
-            current_version, project_type);
+        let input = format!(
+            "The {} commits since the last release were too many to analyze in one request, so they were classified and summarized in {} batches:\n\n{}",
+            total_commits,
+            summaries.len(),
+            summary_list
+        );
+        self.prompt.render(current_version, project_type, &input)
+    }
 
-        // Enhanced system prompt with more detailed guidelines and examples
-        let system_prompt = format!("{}\n\nBased on the commit messages, determine:\n1. The appropriate semantic version bump type (major, minor, patch, or none)\n2. The exact next version number\n3. A well-formatted changelog in Markdown format\n\nFollow these semantic versioning rules carefully:\n- MAJOR version bump (x.0.0): Reserved for backwards-incompatible API changes, breaking changes, or significant rewrites\n  Example commits: \"BREAKING CHANGE: Remove deprecated API\", \"Complete rewrite of core functionality\"\n- MINOR version bump (0.x.0): For backwards-compatible new features or significant improvements\n  Example commits: \"Add new search functionality\", \"Implement caching system\", \"New CLI option for verbose output\"\n- PATCH version bump (0.0.x): For backwards-compatible bug fixes, performance improvements, or minor changes\n  Example commits: \"Fix null pointer exception\", \"Correct typo in error message\", \"Optimize database query\"\n- NO bump (none): For changes that don't affect the code functionality (docs, tests, CI/CD, refactoring)\n  Example commits: \"Update README\", \"Add unit tests\", \"Configure GitHub Actions\", \"Refactor variable names\"\n\nFor the changelog:\n- Group related changes together (e.g., group all bug fixes)\n- Use clear, concise language focusing on the impact of the change\n- Start each entry with a present-tense verb (Add, Fix, Update, etc.)\n\nReturn your analysis as a JSON object with this exact structure:\n```json\n{{\n  \"bump\": \"major|minor|patch|none\",\n  \"next_version\": \"x.y.z\",\n  \"changelog\": \"- Change 1\\n- Change 2\\n...\"\n}}\n```\n\nYour response should be strictly in this JSON format without any additional text.", intro);
+    /// Constructs the map-step prompt asking the model to classify and summarize one batch of commits.
+    fn build_batch_prompt(
+        &self,
+        current_version: &str,
+        project_type: ProjectType,
+        batch: &[Commit],
+        batch_number: usize,
+        batch_total: usize,
+        classify: bool,
+    ) -> Vec<ChatMessage> {
+        let mut system_prompt = format!("You are an AI assistant specializing in software versioning and release notes. You are given batch {} of {} of the git commit messages since the last release (currently version {}) of a {} project. A later step will combine the summaries of all batches into the final version bump and changelog.\n\n{}\n\nReturn a JSON object with this exact structure:\n```json\n{{\n  \"bump\": \"major|minor|patch|none\",\n  \"summary\": \"- Change 1\\n- Change 2\\n...\"\n}}\n```\nwhere \"bump\" is the highest bump required by any commit in this batch and \"summary\" lists every user-facing change as Markdown bullet points, each ending with the ID of its commit in parentheses. Mention breaking changes explicitly and omit changes that don't affect users.\n\n{}\n\nYour response should be strictly in this JSON format without any additional text.",
+            batch_number, batch_total, current_version, project_type, self.prompt.policy(current_version, project_type), UNTRUSTED_DATA_NOTICE);
+        if classify {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(CLASSIFICATION_FORMAT);
+        }
+        if let Some(inspector) = &self.inspector {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(&inspector.instructions());
+        }
 
-        // Enhanced user prompt with clearer instructions
         let user_prompt = format!(
-            "Current version: {}\nProject type: {}\n\nAnalyze these commit messages to determine semantic version changes:\n\n{}\n\nProvide ONLY the requested JSON with bump type, next version, and markdown changelog entries.",
-            current_version, project_type, commit_list
+            "Classify and summarize these commit messages:\n\n{}\n\nProvide ONLY the requested JSON with bump type and summary.",
+            format_commit_list(batch)
         );
 
-        vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt,
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: user_prompt,
-            },
-        ]
+        vec![ChatMessage::system(system_prompt), ChatMessage::user(user_prompt)]
     }
 
-    /// Calls the OpenAI API, parses the response, validates it, and returns the suggestion.
+    /// Sends the conversation and validates the reply, asking the model to correct
+    /// invalid answers up to the configured number of repair attempts.
+    ///
+    /// With `inspect`, the model may call the inspector's tools before answering. Tool calls are
+    /// answered in the conversation and don't count as repair attempts; once the budget is used up,
+    /// a tool call is treated as an invalid answer. Requests carry `seed`, usually the client's.
+    async fn complete_with_repair<T, F>(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        schema: JsonSchema,
+        inspect: bool,
+        seed: Option<u64>,
+        validate: F,
+    ) -> Result<T>
+    where
+        F: Fn(&str) -> Result<T>,
+    {
+        // Constrain the reply to the schema where the provider supports it.
+        let response_schema = if self.structured_output && self.provider.supports_json_schema() {
+            Some(schema)
+        } else {
+            None
+        };
+        let inspector = if inspect { self.inspector.as_deref() } else { None };
+
+        let mut repair_attempt = 0;
+        loop {
+            // Log the API request (but not the full prompt which could be large)
+            debug!(
+                "Sending request to provider '{}' with model {} (~{} prompt tokens, structured output: {})",
+                self.provider.name(),
+                self.model,
+                chunking::estimate_message_tokens(&messages),
+                response_schema.is_some()
+            );
+
+            // While tool calls are left the reply may be one, so it can't be held to the answer's schema
+            let tools_open = inspector.is_some_and(CommitInspector::has_budget);
+            let request = CompletionRequest {
+                model: self.model.clone(),
+                messages: messages.clone(),
+                temperature: self.temperature,
+                seed,
+                max_tokens: self.max_tokens,
+                response_schema: if tools_open { None } else { response_schema.clone() },
+            };
+
+            // Make the API request
+            let response = self
+                .provider
+                .complete(&request)
+                .await
+                .context("Failed to get chat completion from the API")?;
+
+            let tool_call = inspector
+                .and_then(|inspector| parse_tool_call(&response.content).map(|call| (inspector, call)));
+            let outcome = match tool_call {
+                Some((inspector, call)) if inspector.has_budget() => {
+                    let result = run_tool_call(inspector, &call);
+                    messages.push(ChatMessage::assistant(response.content));
+                    messages.push(ChatMessage::user(result));
+                    continue;
+                }
+                Some(_) => Err(CommitSenseError::ApiInvalidResponse(
+                    "no tool calls are left, so the answer is needed now".to_string(),
+                )
+                .into()),
+                None => validate(&response.content),
+            };
+
+            match outcome {
+                Ok(value) => return Ok(value),
+                Err(e) if repair_attempt < self.repair_attempts => {
+                    repair_attempt += 1;
+                    warn!(
+                        "AI response failed validation: {:#}. Asking the model to correct it (repair attempt {}/{}).",
+                        e, repair_attempt, self.repair_attempts
+                    );
+                    messages.push(ChatMessage::assistant(response.content));
+                    messages.push(ChatMessage::user(format!(
+                        "Your previous answer could not be used because of this error:\n\n{:#}\n\nCorrect your answer and reply with ONLY the JSON object in the required format.",
+                        e
+                    )));
+                }
+                Err(e) if repair_attempt > 0 => {
+                    return Err(e.context(format!(
+                        "AI response was still invalid after {} repair attempt(s)",
+                        repair_attempt
+                    )));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Map step: classifies and summarizes the commits in batches that each fit the chunk budget.
+    async fn summarize_in_batches(
+        &self,
+        current_version: &str,
+        commits: &[Commit],
+        project_type: ProjectType,
+        classify: bool,
+    ) -> Result<Vec<BatchSummary>> {
+        let batches = chunking::chunk_by_tokens(commits, self.chunk_settings.chunk_tokens, |commit| {
+            chunking::estimate_tokens(&commit.prompt_text())
+        });
+        info!(
+            "Commit range is too large for a single prompt (context window: {} tokens). Summarizing {} commits in {} batches.",
+            self.chunk_settings.context_tokens,
+            commits.len(),
+            batches.len()
+        );
+
+        let mut summaries = Vec::with_capacity(batches.len());
+        for (i, batch) in batches.iter().enumerate() {
+            info!("Summarizing batch {}/{} ({} commits)...", i + 1, batches.len(), batch.len());
+            let messages =
+                self.build_batch_prompt(current_version, project_type, batch, i + 1, batches.len(), classify);
+            let schema = if classify {
+                with_classifications_schema(BatchSummary::json_schema())
+            } else {
+                BatchSummary::json_schema()
+            };
+            let mut summary = self
+                .complete_with_repair(messages, schema, true, self.seed, |content| {
+                    let mut summary = parse_batch_summary(content)?;
+                    if classify {
+                        summary.commits = resolve_classifications(summary.commits, batch)?;
+                    }
+                    Ok(summary)
+                })
+                .await
+                .with_context(|| format!("Failed to summarize commit batch {}/{}", i + 1, batches.len()))?;
+            summary.commit_count = batch.len();
+            summaries.push(summary);
+        }
+        Ok(summaries)
+    }
+
+    /// Draws `samples` independent answers, each validated (and repaired) on its own, and
+    /// decides the bump type by majority vote.
+    ///
+    /// Samples that stay invalid after their repair attempts are left out of the vote;
+    /// the run only fails if none is valid. Other errors (e.g. connection failures) fail immediately.
+    async fn sample_and_vote<F>(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: JsonSchema,
+        inspect: bool,
+        validate: F,
+    ) -> Result<AISuggestion>
+    where
+        F: Fn(&str) -> Result<AISuggestion>,
+    {
+        let mut valid = Vec::with_capacity(self.samples);
+        let mut last_error = None;
+        for sample in 1..=self.samples {
+            info!("Drawing sample {}/{}...", sample, self.samples);
+            // A fixed seed would draw the same answer every time, so each sample gets its own
+            let seed = self.seed.map(|seed| seed.wrapping_add(sample as u64 - 1));
+            let result = self
+                .complete_with_repair(messages.clone(), schema.clone(), inspect, seed, &validate)
+                .await;
+            match result {
+                Ok(suggestion) => valid.push(suggestion),
+                Err(e) if is_invalid_response(&e) => {
+                    warn!("Sample {}/{} is left out of the vote: {:#}", sample, self.samples, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if valid.is_empty() {
+            return Err(last_error
+                .expect("at least one sample was drawn")
+                .context(format!("None of the {} samples produced a valid answer", self.samples)));
+        }
+
+        let (winner, vote) = majority_vote(&valid);
+        let mut suggestion = valid.swap_remove(winner);
+        if vote.low_confidence {
+            warn!(
+                "Samples disagree on the bump type ({}). Treat '{}' as a low-confidence decision.",
+                format_votes(&vote),
+                suggestion.bump_type
+            );
+        } else {
+            info!("Samples agree on bump '{}' ({}).", suggestion.bump_type, format_votes(&vote));
+        }
+        // Disagreement between samples lowers the confidence of the winner
+        suggestion.confidence = suggestion.confidence.map(|c| c * vote.agreement);
+        suggestion.vote = Some(vote);
+        Ok(suggestion)
+    }
+
+    /// Calls the OpenAI API for commit messages without known hashes.
+    /// See `get_version_and_changelog_for_commits`.
     pub async fn get_version_and_changelog(
         &self,
         current_version_str: &str,
         commits: &[String],
         project_type: ProjectType,
+    ) -> Result<AISuggestion> {
+        let commits: Vec<Commit> = commits.iter().map(|msg| Commit::new("", msg.as_str())).collect();
+        self.get_version_and_changelog_for_commits(current_version_str, &commits, project_type)
+            .await
+    }
+
+    /// Calls the OpenAI API, parses the response, validates it, and returns the suggestion.
+    ///
+    /// Each commit is shown to the model with its abbreviated hash, so the rationale can cite it.
+    ///
+    /// If the response fails validation (unparsable JSON, bad bump type, invalid or non-increasing
+    /// version), the model is sent a follow-up turn quoting the error and asked to correct its
+    /// answer, up to the configured number of repair attempts.
+    ///
+    /// If the prompt would not fit the model's context window, the commits are first summarized
+    /// in batches (map) and the suggestion is produced from the batch summaries (reduce).
+    pub async fn get_version_and_changelog_for_commits(
+        &self,
+        current_version_str: &str,
+        commits: &[Commit],
+        project_type: ProjectType,
     ) -> Result<AISuggestion> {
         // Skip API call if no commits to analyze
         if commits.is_empty() {
@@ -121,6 +745,7 @@ impl OpenAIClient {
                 bump_type: "none".to_string(),
                 next_version: current_version_str.to_string(),
                 changelog_markdown: "No changes".to_string(),
+                ..Default::default()
             });
         }
 
@@ -132,130 +757,485 @@ impl OpenAIClient {
             )
         })?;
 
-        // Build the messages for the OpenAI API
-        let messages = self.build_prompt(current_version_str, commits, project_type);
-
-        // Convert our messages to the format expected by openai_api_rs
-        let api_messages = messages.iter().map(|msg| {
-            chat_completion::ChatCompletionMessage {
-                role: match msg.role.as_str() {
-                    "system" => chat_completion::MessageRole::system,
-                    "user" => chat_completion::MessageRole::user,
-                    "assistant" => chat_completion::MessageRole::assistant,
-                    _ => chat_completion::MessageRole::user, // Default to user for unknown roles
-                },
-                content: chat_completion::Content::Text(msg.content.clone()),
-                name: None,
-                tool_calls: None,
-                tool_call_id: None,
-            }
-        }).collect::<Vec<_>>();
-
-        // Log the API request (but not the full prompt which could be large)
-        debug!(
-            "Sending request to OpenAI API with model {}",
-            self.model
-        );
-
-        // Build the OpenAI client
-        let mut client = OpenAIApiClient::builder()
-            .with_api_key(&self.api_key)
-            .build()
-            .map_err(|e| anyhow::anyhow!("Failed to build OpenAI client: {}", e))?;
-
-        // Create the request
-        let req = chat_completion::ChatCompletionRequest::new(
-            self.model.clone(),
-            api_messages,
-        );
-
-        // Make the API request
-        let response = client.chat_completion(req).await
-            .context("Failed to get chat completion from OpenAI API")?;
+        // Commits can only be classified when the model can refer to them by hash
+        let classify = self.classify && commits.iter().all(|c| !c.oid.is_empty());
+        if self.classify && !classify {
+            warn!("Per-commit classifications need commit hashes. Skipping them.");
+        }
 
-        // Extract the assistant's message
-        if response.choices.is_empty() {
-            return Err(CommitSenseError::Api(
-                "OpenAI API returned empty choices array".to_string(),
-            )
-            .into());
+        // Build the messages for the OpenAI API, switching to map-reduce if they don't fit the context window
+        let mut messages = self.build_prompt(current_version_str, commits, project_type, classify);
+        let mut batch_classifications = None;
+        // The reduce step only sees batch summaries, so tools are offered with the commits only
+        let mut inspect = true;
+        let prompt_tokens = chunking::estimate_message_tokens(&messages);
+        if prompt_tokens > self.chunk_settings.prompt_budget() {
+            debug!(
+                "Estimated prompt size {} tokens exceeds budget of {} tokens.",
+                prompt_tokens,
+                self.chunk_settings.prompt_budget()
+            );
+            let mut summaries = self
+                .summarize_in_batches(current_version_str, commits, project_type, classify)
+                .await?;
+            // In map-reduce mode the commits are classified in the map step
+            if classify {
+                batch_classifications = Some(summaries.iter_mut().flat_map(|s| std::mem::take(&mut s.commits)).collect());
+            }
+            messages =
+                self.build_reduce_prompt(current_version_str, &summaries, commits.len(), project_type);
+            inspect = false;
+            let reduce_tokens = chunking::estimate_message_tokens(&messages);
+            if reduce_tokens > self.chunk_settings.prompt_budget() {
+                warn!(
+                    "Batch summaries still need ~{} tokens, more than the budget of {}. Consider a larger chunk size or a model with a bigger context window.",
+                    reduce_tokens,
+                    self.chunk_settings.prompt_budget()
+                );
+            }
         }
 
-        // Get the content from the first choice
-        let assistant_message = match &response.choices[0].message.content {
-            Some(content) => content,
-            None => return Err(CommitSenseError::Api(
-                "OpenAI API returned a message with no content".to_string(),
-            ).into()),
+        let classify_final = classify && batch_classifications.is_none();
+        let schema = if classify_final {
+            with_classifications_schema(AISuggestion::json_schema())
+        } else {
+            AISuggestion::json_schema()
         };
-
-        // Extract the JSON block from the message
-        let json_block = extract_json_block(assistant_message).ok_or_else(|| {
-            CommitSenseError::Api("Could not find a valid JSON block in the API response".to_string())
-        })?;
-
-        // Parse the JSON block into our AISuggestion structure
-        let mut suggestion: AISuggestion = serde_json::from_str(json_block).with_context(|| {
-            format!(
-                "Failed to parse AI suggestion JSON. Raw JSON block: {}",
-                json_block
-            )
-        })?;
-
-        // Validate the AI's response
-        // 1. Check Bump Type is Valid
-        let lower_bump_type = suggestion.bump_type.to_lowercase();
-        if !["major", "minor", "patch", "none"].contains(&lower_bump_type.as_str()) {
-            return Err(CommitSenseError::Api(format!(
-                "Received invalid bump type '{}' from AI. Expected 'major', 'minor', 'patch', or 'none'.",
-                suggestion.bump_type
-            ))
-            .into());
+        let validate = |content: &str| {
+            let mut suggestion = parse_suggestion(content, &current_version)?;
+            suggestion.classifications = if classify_final {
+                resolve_classifications(std::mem::take(&mut suggestion.classifications), commits)?
+            } else {
+                Vec::new()
+            };
+            Ok(suggestion)
+        };
+        let mut suggestion = if self.samples > 1 {
+            self.sample_and_vote(messages, schema, inspect, validate).await?
+        } else {
+            self.complete_with_repair(messages, schema, inspect, self.seed, validate).await?
+        };
+        if let Some(classifications) = batch_classifications {
+            suggestion.classifications = classifications;
         }
-        // Standardize casing for internal use
-        suggestion.bump_type = lower_bump_type;
-
-        // 2. Validate Next Version is Parsable as SemVer
-        let next_version = Version::parse(&suggestion.next_version).with_context(|| {
-            format!(
-                "AI suggested next_version '{}' is not a valid semantic version.",
-                suggestion.next_version
-            )
-        })?;
 
-        // 3. Ensure Next Version > Current Version (unless bump is 'none')
-        if suggestion.bump_type != "none" {
-            if next_version <= current_version {
-                return Err(CommitSenseError::Api(format!(
-                    "AI suggested next_version '{}' which is not greater than current version '{}', despite bump type being '{}'. AI response may be inconsistent.",
-                    suggestion.next_version, current_version_str, suggestion.bump_type
-                )).into());
-            }
-        } else { // Bump type is "none"
-            if next_version != current_version {
+        // 4. Convention Floor (hybrid policy): commits that declare their impact via Conventional Commits
+        // set the minimum bump. The AI may raise it but never lower it.
+        if self.convention_floor {
+            let messages: Vec<String> = commits.iter().map(|c| c.message.clone()).collect();
+            let floor = conventional::required_bump(&messages);
+            let ai_bump: BumpType = suggestion
+                .bump_type
+                .parse()
+                .map_err(CommitSenseError::ApiInvalidResponse)?;
+            if floor > ai_bump {
+                let drivers = conventional::commits_requiring(&messages, floor);
                 warn!(
-                    "AI suggested bump type 'none' but next_version '{}' differs from current version '{}'. Correcting next_version to match current version.",
-                    suggestion.next_version, current_version_str
+                    "AI suggested bump '{}' but commit conventions require at least '{}' (due to: {}). Raising bump to '{}'.",
+                    ai_bump,
+                    floor,
+                    drivers.join("; "),
+                    floor
                 );
-                // Override AI's version if bump is 'none' but version changed
-                suggestion.next_version = current_version_str.to_string();
+                suggestion.rationale = format!(
+                    "{} Raised from '{}' to '{}' because commit conventions require it: {}.",
+                    suggestion.rationale, ai_bump, floor, drivers.join("; ")
+                )
+                .trim_start()
+                .to_string();
+                suggestion.corrections.push(Correction::new(
+                    "bump",
+                    ai_bump,
+                    floor,
+                    format!("commit conventions require at least '{}': {}", floor, drivers.join("; ")),
+                ));
+                suggestion.bump_override = Some(BumpOverride {
+                    ai_bump: ai_bump.to_string(),
+                    applied_bump: floor.to_string(),
+                    commits: drivers,
+                });
+                suggestion.bump_type = floor.to_string();
             }
         }
 
-        // 4. Optional Consistency Check: Compare AI's next_version with a locally calculated one based on bump_type.
-        // This helps detect if the AI followed SemVer increment rules correctly.
+        // 5. Derive next_version from the final bump type, so it always follows SemVer increment rules
+        // even if the AI miscalculated it or the bump was raised above.
         let expected_next =
             version::calculate_expected_version(&current_version, &suggestion.bump_type);
-        if suggestion.bump_type != "none" && expected_next != next_version {
+        if expected_next.to_string() != suggestion.next_version {
             warn!(
-                "AI suggested next_version '{}' differs from the version strictly calculated ('{}') based on bump type '{}' and current version '{}'. Proceeding with AI's suggested version, but this might indicate unexpected AI behavior.",
+                "Suggested next_version '{}' differs from the version calculated ('{}') from bump type '{}' and current version '{}'. Using the calculated version.",
                 suggestion.next_version, expected_next, suggestion.bump_type, current_version_str
             );
+            suggestion.corrections.push(Correction::new(
+                "next_version",
+                &suggestion.next_version,
+                &expected_next,
+                format!("calculated from bump type '{}' and current version '{}'", suggestion.bump_type, current_version_str),
+            ));
+            suggestion.next_version = expected_next.to_string();
         }
 
+        suggestion.model = Some(self.model.clone());
         info!("AI suggestion validated successfully.");
         Ok(suggestion)
     }
+
+    /// Writes the release notes for one audience from an analysis that already decided the bump.
+    ///
+    /// `instructions` describe the audience and style (a notes profile's prompt section). The model
+    /// sees the changelog of the analysis and, if they fit the context window, the commits themselves.
+    pub async fn write_release_notes(
+        &self,
+        current_version: &str,
+        suggestion: &AISuggestion,
+        commits: &[Commit],
+        project_type: ProjectType,
+        instructions: &str,
+    ) -> Result<String> {
+        let system_prompt = format!("You write release notes for a {} project. Version {} is a {} release (the previous version is {}). The version bump has already been decided: don't change or question it.\n\nWrite the notes for this audience and in this style:\n{}\n\n{}\n\nReturn a JSON object with this exact structure:\n```json\n{{\n  \"notes\": \"Markdown release notes\"\n}}\n```\nThe notes are inserted below a version heading, so don't add one.\n\nYour response should be strictly in this JSON format without any additional text.",
+            project_type, suggestion.next_version, suggestion.bump_type, current_version, instructions.trim(), UNTRUSTED_DATA_NOTICE);
+        let changelog = format!("Changelog of the release:\n\n{}", suggestion.changelog_markdown.trim());
+
+        let mut messages = vec![
+            ChatMessage::system(system_prompt.clone()),
+            ChatMessage::user(format!(
+                "{}\n\nCommits in the release:\n\n{}",
+                changelog,
+                format_commit_list(commits)
+            )),
+        ];
+        // Large ranges were summarized in batches; the changelog is all that fits then
+        if chunking::estimate_message_tokens(&messages) > self.chunk_settings.prompt_budget() {
+            debug!("The commits don't fit the release notes prompt. Sending the changelog only.");
+            messages = vec![ChatMessage::system(system_prompt), ChatMessage::user(changelog)];
+        }
+
+        let notes = self
+            .complete_with_repair(messages, ReleaseNotes::json_schema(), false, self.seed, parse_release_notes)
+            .await?;
+        Ok(notes.notes)
+    }
+
+    /// Translates a changelog into `language` (a BCP 47 code such as "de" or "pt-BR"),
+    /// keeping its Markdown structure. The bump decision is not revisited.
+    pub async fn translate_changelog(&self, changelog_markdown: &str, language: &str) -> Result<String> {
+        let system_prompt = format!("You translate software release notes. Translate the changelog given by the user into the language with the BCP 47 code '{}'.\n\nKeep the Markdown structure exactly: the same headings and the same bullet points in the same order. Do not translate code spans, identifiers, file names, command-line flags, commit IDs or version numbers. Do not add, drop or merge entries.\n\nReturn a JSON object with this exact structure:\n```json\n{{\n  \"changelog\": \"- Change 1\\n- Change 2\\n...\"\n}}\n```\n\nYour response should be strictly in this JSON format without any additional text.", language);
+        let messages = vec![
+            ChatMessage::system(system_prompt),
+            ChatMessage::user(format!("Translate this changelog:\n\n{}", changelog_markdown.trim())),
+        ];
+
+        let translation = self
+            .complete_with_repair(messages, Translation::json_schema(), false, self.seed, |content| {
+                parse_translation(content, changelog_markdown)
+            })
+            .await?;
+        info!("Translated the changelog to '{}'.", language);
+        Ok(translation.changelog)
+    }
+}
+
+/// Decides the bump type by majority vote over validated suggestions.
+///
+/// Returns the index of the suggestion to use (the first one that voted for the winner) and the
+/// vote. Ties go to the higher bump, since under-bumping breaks downstream users while
+/// over-bumping only costs a version number.
+pub fn majority_vote(suggestions: &[AISuggestion]) -> (usize, BumpVote) {
+    let mut counts: Vec<(BumpType, usize)> = Vec::new();
+    for suggestion in suggestions {
+        let bump: BumpType = suggestion.bump_type.parse().unwrap_or(BumpType::None);
+        match counts.iter_mut().find(|(b, _)| *b == bump) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((bump, 1)),
+        }
+    }
+    // Highest bump first, so the first maximum wins ties
+    counts.sort_by_key(|(bump, _)| std::cmp::Reverse(*bump));
+    let (winner, winner_votes) = counts
+        .iter()
+        .copied()
+        .fold((BumpType::None, 0), |best, c| if c.1 > best.1 { c } else { best });
+
+    let index = suggestions
+        .iter()
+        .position(|s| s.bump_type.parse::<BumpType>().ok() == Some(winner))
+        .unwrap_or(0);
+    let agreement = winner_votes as f64 / suggestions.len().max(1) as f64;
+    let vote = BumpVote {
+        samples: suggestions.len(),
+        votes: counts.into_iter().map(|(b, n)| (b.to_string(), n)).collect(),
+        agreement,
+        low_confidence: agreement < LOW_CONFIDENCE_AGREEMENT,
+    };
+    (index, vote)
+}
+
+/// Formats the votes for logs and console output, e.g. "minor: 2, patch: 1".
+pub fn format_votes(vote: &BumpVote) -> String {
+    vote.votes
+        .iter()
+        .map(|(bump, n)| format!("{}: {}", bump, n))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Returns whether the error means the model's answer was unusable, as opposed to a failed request.
+fn is_invalid_response(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| matches!(cause.downcast_ref::<CommitSenseError>(), Some(CommitSenseError::ApiInvalidResponse(_))))
+}
+
+/// Formats commit messages as escaped `<commit>` data blocks for inclusion in a prompt.
+/// Blocks of commits containing instruction-like text are flagged for the model.
+fn format_commit_list(commits: &[Commit]) -> String {
+    commits
+        .iter()
+        .map(|commit| {
+            let mut attributes = String::new();
+            if !commit.oid.is_empty() {
+                attributes.push_str(&format!(" id=\"{}\"", commit.short_oid()));
+            }
+            if !injection::scan(&commit.message).is_empty() {
+                attributes.push_str(" flagged=\"possible-injection\"");
+            }
+            format!("<commit{}>\n{}\n</commit>", attributes, escape_commit_text(commit.prompt_text().trim()))
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Escapes commit text so it can't close its `<commit>` block or open a new one.
+fn escape_commit_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Returns the tool call if the reply asks to run a tool instead of answering.
+fn parse_tool_call(assistant_message: &str) -> Option<ToolCall> {
+    let json = extract_json_block(assistant_message)?;
+    serde_json::from_str::<ToolCallReply>(json).ok().map(|reply| reply.tool_call)
+}
+
+/// Runs a tool call of the model and returns the next user message: the escaped result (or error)
+/// as a `<tool_result>` data block, followed by what is left of the budget.
+fn run_tool_call(inspector: &CommitInspector, call: &ToolCall) -> String {
+    let result = match inspector.call(call) {
+        Ok(output) => {
+            let usage = inspector.usage();
+            info!(
+                "The model inspected {} ({}/{} tool calls).",
+                call.describe(),
+                usage.calls,
+                inspector.budget().max_calls
+            );
+            escape_commit_text(&output)
+        }
+        Err(e) => {
+            warn!("Tool call {} failed: {:#}", call.describe(), e);
+            escape_commit_text(&format!("error: {:#}", e))
+        }
+    };
+
+    let usage = inspector.usage();
+    let budget = inspector.budget();
+    let left = if inspector.has_budget() {
+        format!(
+            "You have {} tool call(s) and {} bytes of results left.",
+            budget.max_calls - usage.calls,
+            budget.max_bytes - usage.bytes
+        )
+    } else {
+        "No tool calls are left. Reply with the final answer now, in the required format.".to_string()
+    };
+    format!("<tool_result>\n{}\n</tool_result>\n\n{}", result, left)
+}
+
+/// Maps the abbreviated hashes the model answered with to the commits' full hashes.
+/// Entries for unknown commits are rejected so the model can correct them; duplicates are dropped.
+fn resolve_classifications(
+    classifications: Vec<CommitClassification>,
+    commits: &[Commit],
+) -> Result<Vec<CommitClassification>> {
+    let mut resolved: Vec<CommitClassification> = Vec::with_capacity(classifications.len());
+    for mut classification in classifications {
+        let id = classification.oid.trim();
+        let commit = commits
+            .iter()
+            .find(|c| c.oid == id || (id.len() >= 4 && c.oid.starts_with(id)))
+            .ok_or_else(|| {
+                CommitSenseError::ApiInvalidResponse(format!(
+                    "The \"commits\" array refers to unknown commit ID '{}'. Use the IDs given in the commit list.",
+                    id
+                ))
+            })?;
+        if resolved.iter().any(|r| r.oid == commit.oid) {
+            warn!("Ignoring duplicate classification of commit {}", commit.short_oid());
+            continue;
+        }
+        classification.oid = commit.oid.clone();
+        classification.source = model_source();
+        resolved.push(classification);
+    }
+    Ok(resolved)
+}
+
+/// Extracts release notes from the assistant's reply.
+fn parse_release_notes(assistant_message: &str) -> Result<ReleaseNotes> {
+    let json_block = extract_json_block(assistant_message).ok_or_else(|| {
+        CommitSenseError::ApiInvalidResponse(
+            "Could not find a valid JSON block in the API response".to_string(),
+        )
+    })?;
+    let notes: ReleaseNotes = serde_json::from_str(json_block).map_err(|e| {
+        CommitSenseError::ApiInvalidResponse(format!(
+            "Failed to parse release notes JSON ({}). Raw JSON block: {}",
+            e, json_block
+        ))
+    })?;
+    if notes.notes.trim().is_empty() {
+        return Err(CommitSenseError::ApiInvalidResponse("The release notes are empty".to_string()).into());
+    }
+    Ok(notes)
+}
+
+/// Extracts a translation from the assistant's reply and checks that it kept the structure
+/// (headings and bullet points) of the original changelog.
+fn parse_translation(assistant_message: &str, original: &str) -> Result<Translation> {
+    let json_block = extract_json_block(assistant_message).ok_or_else(|| {
+        CommitSenseError::ApiInvalidResponse(
+            "Could not find a valid JSON block in the API response".to_string(),
+        )
+    })?;
+    let translation: Translation = serde_json::from_str(json_block).map_err(|e| {
+        CommitSenseError::ApiInvalidResponse(format!(
+            "Failed to parse translation JSON ({}). Raw JSON block: {}",
+            e, json_block
+        ))
+    })?;
+    if translation.changelog.trim().is_empty() {
+        return Err(CommitSenseError::ApiInvalidResponse("The translated changelog is empty".to_string()).into());
+    }
+
+    let structure = |text: &str| {
+        let lines: Vec<&str> = text.lines().map(str::trim_start).collect();
+        let headings = lines.iter().filter(|l| l.starts_with('#')).count();
+        let bullets = lines.iter().filter(|l| l.starts_with("- ") || l.starts_with("* ")).count();
+        (headings, bullets)
+    };
+    let (expected_headings, expected_bullets) = structure(original);
+    let (headings, bullets) = structure(&translation.changelog);
+    if (headings, bullets) != (expected_headings, expected_bullets) {
+        return Err(CommitSenseError::ApiInvalidResponse(format!(
+            "The translation has {} heading(s) and {} bullet point(s), but the changelog has {} and {}. Translate every heading and entry, one for one.",
+            headings, bullets, expected_headings, expected_bullets
+        ))
+        .into());
+    }
+    Ok(translation)
+}
+
+/// Extracts and validates a batch summary from the assistant's reply in the map step.
+fn parse_batch_summary(assistant_message: &str) -> Result<BatchSummary> {
+    let json_block = extract_json_block(assistant_message).ok_or_else(|| {
+        CommitSenseError::ApiInvalidResponse(
+            "Could not find a valid JSON block in the API response".to_string(),
+        )
+    })?;
+    let mut summary: BatchSummary = serde_json::from_str(json_block).map_err(|e| {
+        CommitSenseError::ApiInvalidResponse(format!(
+            "Failed to parse batch summary JSON ({}). Raw JSON block: {}",
+            e, json_block
+        ))
+    })?;
+    summary.bump = summary
+        .bump
+        .parse::<BumpType>()
+        .map_err(CommitSenseError::ApiInvalidResponse)?
+        .to_string();
+    Ok(summary)
+}
+
+/// Extracts the suggestion JSON from the assistant's reply and validates it against the current version.
+/// Every error returned here describes a problem the model can fix in a repair turn.
+fn parse_suggestion(assistant_message: &str, current_version: &Version) -> Result<AISuggestion> {
+    // Extract the JSON block from the message
+    let json_block = extract_json_block(assistant_message).ok_or_else(|| {
+        CommitSenseError::ApiInvalidResponse(
+            "Could not find a valid JSON block in the API response".to_string(),
+        )
+    })?;
+
+    // Parse the JSON block into our AISuggestion structure
+    let mut suggestion: AISuggestion = serde_json::from_str(json_block).map_err(|e| {
+        CommitSenseError::ApiInvalidResponse(format!(
+            "Failed to parse AI suggestion JSON ({}). Raw JSON block: {}",
+            e, json_block
+        ))
+    })?;
+    // Corrections are recorded by CommitSense itself, never taken from the model
+    suggestion.corrections.clear();
+
+    // Validate the AI's response
+    // 1. Check Bump Type is Valid
+    let lower_bump_type = suggestion.bump_type.to_lowercase();
+    if !["major", "minor", "patch", "none"].contains(&lower_bump_type.as_str()) {
+        return Err(CommitSenseError::ApiInvalidResponse(format!(
+            "Received invalid bump type '{}' from AI. Expected 'major', 'minor', 'patch', or 'none'.",
+            suggestion.bump_type
+        ))
+        .into());
+    }
+    // Standardize casing for internal use
+    suggestion.bump_type = lower_bump_type;
+
+    // 2. Validate Next Version is Parsable as SemVer
+    let next_version = Version::parse(&suggestion.next_version).map_err(|e| {
+        CommitSenseError::ApiInvalidResponse(format!(
+            "AI suggested next_version '{}' is not a valid semantic version ({}).",
+            suggestion.next_version, e
+        ))
+    })?;
+
+    // 3. Ensure Next Version > Current Version (unless bump is 'none')
+    if suggestion.bump_type != "none" {
+        if next_version <= *current_version {
+            return Err(CommitSenseError::ApiInvalidResponse(format!(
+                "AI suggested next_version '{}' which is not greater than current version '{}', despite bump type being '{}'. AI response may be inconsistent.",
+                suggestion.next_version, current_version, suggestion.bump_type
+            )).into());
+        }
+    } else if next_version != *current_version { // Bump type is "none"
+        warn!(
+            "AI suggested bump type 'none' but next_version '{}' differs from current version '{}'. Correcting next_version to match current version.",
+            suggestion.next_version, current_version
+        );
+        // Override AI's version if bump is 'none' but version changed
+        suggestion.corrections.push(Correction::new(
+            "next_version",
+            &suggestion.next_version,
+            current_version,
+            "bump type is 'none', so the version stays the same".to_string(),
+        ));
+        suggestion.next_version = current_version.to_string();
+    }
+
+    // 4. Confidence must be a probability
+    if let Some(confidence) = suggestion.confidence {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(CommitSenseError::ApiInvalidResponse(format!(
+                "AI suggested confidence {} which is outside the range 0.0 to 1.0.",
+                confidence
+            ))
+            .into());
+        }
+    }
+
+    // Fields set by CommitSense itself are never taken from the model
+    suggestion.bump_override = None;
+    suggestion.vote = None;
+
+    Ok(suggestion)
 }
 
 /// Helper function to extract a JSON block (`{...}`) from a potentially larger string.
@@ -274,17 +1254,48 @@ fn extract_json_block(text: &str) -> Option<&str> {
         }
     }
 
-    // If no markdown block found or it wasn't valid JSON, find the first '{' and last '}'
-    if let Some(start) = text.find('{') {
-        if let Some(end) = text.rfind('}') {
-            if end > start {
-                debug!("Extracted JSON by finding first '{{' and last '}}'");
-                // Return the substring including the braces
-                return Some(&text[start..=end]);
+    // If no markdown block found or it wasn't valid JSON, take the first balanced `{...}` object
+    // that parses as JSON, skipping over braces inside strings and stray braces in prose.
+    let mut search_from = 0;
+    while let Some(offset) = text[search_from..].find('{') {
+        let start = search_from + offset;
+        if let Some(end) = find_matching_brace(&text[start..]) {
+            let candidate = &text[start..=start + end];
+            if serde_json::from_str::<serde_json::Value>(candidate).is_ok() {
+                debug!("Extracted JSON by matching balanced braces");
+                return Some(candidate);
             }
         }
+        search_from = start + 1;
     }
 
     debug!("Could not find a recognizable JSON block in the text.");
     None // No JSON block found
-}
\ No newline at end of file
+}
+
+/// Returns the byte offset of the `}` closing the object that starts at the beginning of `text`.
+fn find_matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escape_next = false;
+
+    for (i, c) in text.char_indices() {
+        if escape_next {
+            escape_next = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escape_next = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
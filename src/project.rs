@@ -11,8 +11,6 @@ use toml::Value as TomlValue;
 /// Handles reading and writing version information to the appropriate file (`Cargo.toml` or `package.json`).
 #[derive(Debug)]
 pub struct Project {
-    /// The root path of the project or sub-package being processed.
-    root_path: PathBuf,
     /// The type of project (Rust or JavaScript/TypeScript).
     project_type: ProjectType,
     /// The path to the file containing the version number (e.g., `Cargo.toml` or `package.json`).
@@ -77,7 +75,6 @@ impl Project {
             version_file.display()
         );
         Ok(Project {
-            root_path: path.to_path_buf(),
             project_type,
             version_file,
         })
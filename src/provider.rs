@@ -0,0 +1,753 @@
+use crate::cli::ProviderKind;
+use crate::config::ProviderSettings;
+use crate::error::CommitSenseError;
+use anyhow::Result;
+use async_trait::async_trait;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// --- Provider-Agnostic Types ---
+
+/// Represents a single message in the chat conversation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    /// Creates a message with the `system` role.
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    /// Creates a message with the `user` role.
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    /// Creates a message with the `assistant` role.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// A JSON schema the reply must conform to, for backends that support structured output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSchema {
+    /// A short identifier for the schema (required by OpenAI's `response_format`).
+    pub name: String,
+    /// The JSON schema document.
+    pub schema: serde_json::Value,
+}
+
+/// A chat completion request, independent of the wire format of any particular backend.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionRequest {
+    /// The model identifier to send to the backend.
+    pub model: String,
+    /// The conversation so far (system prompt first).
+    pub messages: Vec<ChatMessage>,
+    /// Optional sampling temperature.
+    pub temperature: Option<f32>,
+    /// Optional seed, for backends that can sample reproducibly.
+    pub seed: Option<u64>,
+    /// Optional limit on the number of tokens generated.
+    pub max_tokens: Option<u32>,
+    /// Optional schema constraining the reply. Only set when the provider supports structured output.
+    pub response_schema: Option<JsonSchema>,
+}
+
+/// Token counts reported by the backend for a request.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Tokens in the prompt (all messages sent).
+    pub prompt_tokens: u64,
+    /// Tokens in the generated reply.
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Returns the sum of prompt and completion tokens.
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// The result of a chat completion request.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionResponse {
+    /// The text content of the assistant's reply.
+    pub content: String,
+    /// Token counts, if the backend reported them.
+    pub usage: Option<TokenUsage>,
+}
+
+/// A backend capable of answering chat completion requests.
+///
+/// Implementations own everything specific to a backend: the endpoint layout,
+/// how credentials are attached and the shape of the request/response bodies.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// A short name for the provider, used in log messages.
+    fn name(&self) -> &str;
+
+    /// Whether the backend can constrain its reply to `CompletionRequest::response_schema`.
+    fn supports_json_schema(&self) -> bool {
+        false
+    }
+
+    /// Sends the request to the backend and returns the assistant's reply.
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse>;
+}
+
+// --- OpenAI-Compatible Provider ---
+
+/// Describes how the API key is attached to outgoing requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <key>` (OpenAI and most compatible gateways).
+    Bearer,
+    /// The raw key in a custom header, e.g. `api-key: <key>`.
+    Header(String),
+    /// No authentication header is sent.
+    None,
+}
+
+/// Represents the request body for the Chat Completions API.
+#[derive(Serialize, Debug)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+}
+
+/// Represents the overall structure of the response from the Chat Completions API.
+#[derive(Deserialize, Debug)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+    #[serde(default)]
+    usage: Option<ChatCompletionUsage>,
+}
+
+/// Token counts in a chat completion response.
+#[derive(Deserialize, Debug)]
+struct ChatCompletionUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+/// Represents a single completion choice provided by the model.
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+/// The message inside a completion choice. `content` may be null for some responses.
+#[derive(Deserialize, Debug)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+}
+
+/// The `api-version` sent to Azure OpenAI when none is configured.
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// Provider for OpenAI's Chat Completions API and any gateway that speaks the same protocol.
+pub struct OpenAiCompatibleProvider {
+    http: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    auth_style: AuthStyle,
+    /// The Azure OpenAI deployment, which replaces the OpenAI URL layout.
+    deployment: Option<String>,
+    /// The `api-version` query parameter, if any.
+    api_version: Option<String>,
+    /// Extra headers sent with every request.
+    headers: reqwest::header::HeaderMap,
+}
+
+impl OpenAiCompatibleProvider {
+    /// Creates a provider that posts to `<api_url>/chat/completions` using bearer authentication.
+    pub fn new(api_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        OpenAiCompatibleProvider {
+            http: reqwest::Client::new(),
+            api_url: api_url.into(),
+            api_key: api_key.into(),
+            auth_style: AuthStyle::Bearer,
+            deployment: None,
+            api_version: None,
+            headers: reqwest::header::HeaderMap::new(),
+        }
+    }
+
+    /// Creates a provider for an Azure OpenAI deployment. `api_url` is the resource endpoint
+    /// (`https://<resource>.openai.azure.com`), and the key is sent in the `api-key` header.
+    pub fn azure(api_url: impl Into<String>, api_key: impl Into<String>, deployment: impl Into<String>) -> Self {
+        let mut provider = Self::new(api_url, api_key)
+            .with_auth_style(AuthStyle::Header("api-key".to_string()))
+            .with_api_version(DEFAULT_AZURE_API_VERSION);
+        provider.deployment = Some(deployment.into());
+        provider
+    }
+
+    /// Overrides how the API key is attached to requests.
+    pub fn with_auth_style(mut self, auth_style: AuthStyle) -> Self {
+        self.auth_style = auth_style;
+        self
+    }
+
+    /// Sends `api-version=<version>` as a query parameter with every request.
+    pub fn with_api_version(mut self, version: impl Into<String>) -> Self {
+        self.api_version = Some(version.into());
+        self
+    }
+
+    /// Sends extra headers with every request, e.g. for a gateway that routes or bills by header.
+    pub fn with_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Returns the full URL of the chat completions endpoint.
+    pub fn endpoint(&self) -> String {
+        let base = self.api_url.trim_end_matches('/');
+        let mut endpoint = match &self.deployment {
+            Some(deployment) => format!("{}/openai/deployments/{}/chat/completions", base, deployment),
+            None => format!("{}/chat/completions", base),
+        };
+        if let Some(version) = &self.api_version {
+            endpoint.push_str(&format!("?api-version={}", version));
+        }
+        endpoint
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        if self.deployment.is_some() {
+            "azure"
+        } else {
+            "openai"
+        }
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let endpoint = self.endpoint();
+        debug!("POST {} (model {})", endpoint, request.model);
+
+        let body = ChatCompletionRequest {
+            model: &request.model,
+            messages: &request.messages,
+            temperature: request.temperature,
+            seed: request.seed,
+            max_tokens: request.max_tokens,
+            response_format: request.response_schema.as_ref().map(|s| {
+                serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": s.name, "strict": true, "schema": s.schema },
+                })
+            }),
+        };
+
+        let mut http_request = self.http.post(&endpoint).headers(self.headers.clone()).json(&body);
+        http_request = match &self.auth_style {
+            AuthStyle::Bearer => http_request.bearer_auth(&self.api_key),
+            AuthStyle::Header(name) => http_request.header(name.as_str(), &self.api_key),
+            AuthStyle::None => http_request,
+        };
+
+        let text = send_request(http_request, &endpoint).await?;
+        let parsed: ChatCompletionResponse = serde_json::from_str(&text).map_err(|e| {
+            CommitSenseError::ApiInvalidResponse(format!("Failed to parse chat completion response: {}", e))
+        })?;
+
+        let choice = parsed.choices.into_iter().next().ok_or_else(|| {
+            CommitSenseError::ApiInvalidResponse("API returned empty choices array".to_string())
+        })?;
+        let content = choice.message.content.ok_or_else(|| {
+            CommitSenseError::ApiInvalidResponse("API returned a message with no content".to_string())
+        })?;
+
+        let usage = parsed.usage.map(|u| TokenUsage {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+        });
+
+        Ok(CompletionResponse { content, usage })
+    }
+}
+
+// --- Local Model Providers ---
+
+/// Represents the request body for Ollama's `/api/chat` endpoint.
+#[derive(Serialize, Debug)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    /// A JSON schema for structured output (Ollama 0.5+).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a serde_json::Value>,
+}
+
+/// Model parameters accepted by Ollama under the `options` key.
+#[derive(Serialize, Debug)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+/// Represents a non-streaming response from Ollama's `/api/chat` endpoint.
+#[derive(Deserialize, Debug)]
+struct OllamaChatResponse {
+    message: ChatMessage,
+    /// Number of prompt tokens evaluated.
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    /// Number of tokens generated.
+    #[serde(default)]
+    eval_count: Option<u64>,
+}
+
+/// Provider for a locally hosted Ollama server.
+pub struct OllamaProvider {
+    http: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+}
+
+impl OllamaProvider {
+    /// Creates a provider that posts to `<api_url>/api/chat`.
+    /// The API key is optional and only sent (as a bearer token) when present, e.g. behind a reverse proxy.
+    pub fn new(api_url: impl Into<String>, api_key: Option<String>) -> Self {
+        OllamaProvider {
+            http: reqwest::Client::new(),
+            api_url: api_url.into(),
+            api_key,
+        }
+    }
+
+    /// Returns the full URL of the chat endpoint.
+    pub fn endpoint(&self) -> String {
+        format!("{}/api/chat", self.api_url.trim_end_matches('/'))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let endpoint = self.endpoint();
+        debug!("POST {} (model {})", endpoint, request.model);
+
+        let body = OllamaChatRequest {
+            model: &request.model,
+            messages: &request.messages,
+            stream: false,
+            options: (request.temperature.is_some() || request.seed.is_some() || request.max_tokens.is_some())
+                .then_some(OllamaOptions {
+                    temperature: request.temperature,
+                    seed: request.seed,
+                    num_predict: request.max_tokens,
+                }),
+            format: request.response_schema.as_ref().map(|s| &s.schema),
+        };
+
+        let mut http_request = self.http.post(&endpoint).json(&body);
+        if let Some(key) = &self.api_key {
+            http_request = http_request.bearer_auth(key);
+        }
+
+        let text = send_request(http_request, &endpoint).await?;
+        let parsed: OllamaChatResponse = serde_json::from_str(&text).map_err(|e| {
+            CommitSenseError::ApiInvalidResponse(format!("Failed to parse Ollama chat response: {}", e))
+        })?;
+
+        let usage = match (parsed.prompt_eval_count, parsed.eval_count) {
+            (None, None) => None,
+            (prompt, completion) => Some(TokenUsage {
+                prompt_tokens: prompt.unwrap_or(0),
+                completion_tokens: completion.unwrap_or(0),
+            }),
+        };
+
+        Ok(CompletionResponse {
+            content: parsed.message.content,
+            usage,
+        })
+    }
+}
+
+/// Represents the request body for the llama.cpp server's `/completion` endpoint.
+#[derive(Serialize, Debug)]
+struct LlamaCppCompletionRequest<'a> {
+    prompt: String,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n_predict: Option<u32>,
+    /// A JSON schema the server converts into a sampling grammar.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<&'a serde_json::Value>,
+}
+
+/// Represents a non-streaming response from the llama.cpp server's `/completion` endpoint.
+#[derive(Deserialize, Debug)]
+struct LlamaCppCompletionResponse {
+    content: String,
+    /// Number of prompt tokens evaluated.
+    #[serde(default)]
+    tokens_evaluated: Option<u64>,
+    /// Number of tokens generated.
+    #[serde(default)]
+    tokens_predicted: Option<u64>,
+}
+
+/// Provider for a locally hosted llama.cpp HTTP server.
+///
+/// The `/completion` endpoint takes a raw prompt, so the chat messages are flattened
+/// into a role-labelled transcript ending with an open assistant turn.
+pub struct LlamaCppProvider {
+    http: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+}
+
+impl LlamaCppProvider {
+    /// Creates a provider that posts to `<api_url>/completion`.
+    /// The API key is optional and only sent (as a bearer token) when present.
+    pub fn new(api_url: impl Into<String>, api_key: Option<String>) -> Self {
+        LlamaCppProvider {
+            http: reqwest::Client::new(),
+            api_url: api_url.into(),
+            api_key,
+        }
+    }
+
+    /// Returns the full URL of the completion endpoint.
+    pub fn endpoint(&self) -> String {
+        format!("{}/completion", self.api_url.trim_end_matches('/'))
+    }
+
+    /// Flattens chat messages into a single prompt string.
+    fn flatten_messages(messages: &[ChatMessage]) -> String {
+        let mut prompt = messages
+            .iter()
+            .map(|msg| {
+                let label = match msg.role.as_str() {
+                    "system" => "System",
+                    "assistant" => "Assistant",
+                    _ => "User",
+                };
+                format!("{}: {}", label, msg.content)
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        prompt.push_str("\n\nAssistant:");
+        prompt
+    }
+}
+
+#[async_trait]
+impl LlmProvider for LlamaCppProvider {
+    fn name(&self) -> &str {
+        "llamacpp"
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let endpoint = self.endpoint();
+        // llama.cpp serves whichever model it was started with, so the model name is informational only.
+        debug!("POST {} (model {})", endpoint, request.model);
+
+        let body = LlamaCppCompletionRequest {
+            prompt: Self::flatten_messages(&request.messages),
+            stream: false,
+            temperature: request.temperature,
+            seed: request.seed,
+            n_predict: request.max_tokens,
+            json_schema: request.response_schema.as_ref().map(|s| &s.schema),
+        };
+
+        let mut http_request = self.http.post(&endpoint).json(&body);
+        if let Some(key) = &self.api_key {
+            http_request = http_request.bearer_auth(key);
+        }
+
+        let text = send_request(http_request, &endpoint).await?;
+        let parsed: LlamaCppCompletionResponse = serde_json::from_str(&text).map_err(|e| {
+            CommitSenseError::ApiInvalidResponse(format!("Failed to parse llama.cpp completion response: {}", e))
+        })?;
+
+        let usage = match (parsed.tokens_evaluated, parsed.tokens_predicted) {
+            (None, None) => None,
+            (prompt, completion) => Some(TokenUsage {
+                prompt_tokens: prompt.unwrap_or(0),
+                completion_tokens: completion.unwrap_or(0),
+            }),
+        };
+
+        Ok(CompletionResponse {
+            content: parsed.content,
+            usage,
+        })
+    }
+}
+
+// --- Shared HTTP Helpers ---
+
+/// Sends a prepared request and returns the response body.
+/// Transport failures and non-success statuses are classified into the `CommitSenseError::Api*` variants.
+async fn send_request(http_request: reqwest::RequestBuilder, endpoint: &str) -> Result<String> {
+    let response = http_request
+        .send()
+        .await
+        .map_err(|e| classify_transport_error(e, endpoint))?;
+
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let text = response
+        .text()
+        .await
+        .map_err(|e| classify_transport_error(e, endpoint))?;
+    if !status.is_success() {
+        let message = format!(
+            "API request to {} failed with status {}: {}",
+            endpoint, status, text
+        );
+        return Err(classify_status(status, retry_after, message).into());
+    }
+    Ok(text)
+}
+
+/// Maps a reqwest transport error to the matching error variant.
+fn classify_transport_error(error: reqwest::Error, endpoint: &str) -> CommitSenseError {
+    let message = format!("Request to {} failed: {}", endpoint, error);
+    if error.is_timeout() {
+        CommitSenseError::ApiTimeout(message)
+    } else if error.is_connect() {
+        CommitSenseError::ApiConnection(message)
+    } else {
+        CommitSenseError::ApiRequest(message)
+    }
+}
+
+/// Maps a non-success HTTP status to the matching error variant.
+fn classify_status(
+    status: reqwest::StatusCode,
+    retry_after: Option<Duration>,
+    message: String,
+) -> CommitSenseError {
+    match status.as_u16() {
+        401 | 403 => CommitSenseError::ApiAuth(message),
+        408 => CommitSenseError::ApiTimeout(message),
+        429 => CommitSenseError::ApiRateLimit {
+            message,
+            retry_after,
+        },
+        code @ 500..=599 => CommitSenseError::ApiServer {
+            status: code,
+            message,
+            retry_after,
+        },
+        _ => CommitSenseError::ApiRequest(message),
+    }
+}
+
+/// Parses a `Retry-After` header value, given either as delay-seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    // A date in the past means "retry now"
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Returns true if the error means the backend is unavailable (connection failure, timeout
+/// or server error), as opposed to the backend rejecting the request or answering badly.
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<CommitSenseError>(),
+            Some(
+                CommitSenseError::ApiConnection(_)
+                    | CommitSenseError::ApiTimeout(_)
+                    | CommitSenseError::ApiServer { .. }
+            )
+        )
+    })
+}
+
+/// Returns true if the error comes from the backend or the model's answers (any `Api*` error),
+/// as opposed to a local problem such as an invalid current version. A fallback model may succeed.
+pub fn is_api_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<CommitSenseError>(),
+            Some(
+                CommitSenseError::ApiAuth(_)
+                    | CommitSenseError::ApiRateLimit { .. }
+                    | CommitSenseError::ApiTimeout(_)
+                    | CommitSenseError::ApiServer { .. }
+                    | CommitSenseError::ApiConnection(_)
+                    | CommitSenseError::ApiInvalidResponse(_)
+                    | CommitSenseError::ApiRequest(_)
+            )
+        )
+    })
+}
+
+// --- Provider Construction ---
+
+/// Builds the provider selected on the command line.
+///
+/// # Arguments
+/// * `kind` - Which backend to talk to.
+/// * `api_url` - Base URL of the backend; `None` uses the backend's conventional default.
+/// * `api_key` - API key; required for OpenAI-compatible backends, optional for local ones.
+pub fn create_provider(
+    kind: ProviderKind,
+    api_url: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<Box<dyn LlmProvider>> {
+    create_provider_with_settings(kind, api_url, api_key, &ProviderSettings::default())
+}
+
+/// Builds the provider selected on the command line, applying the `[provider]` section of the
+/// configuration file (Azure deployment, API version, auth header and extra headers).
+///
+/// The settings only apply to OpenAI-compatible backends; they are rejected for local ones.
+pub fn create_provider_with_settings(
+    kind: ProviderKind,
+    api_url: Option<&str>,
+    api_key: Option<&str>,
+    settings: &ProviderSettings,
+) -> Result<Box<dyn LlmProvider>> {
+    let api_url = match api_url.or(kind.default_api_url()) {
+        Some(url) => url.to_string(),
+        None => {
+            return Err(CommitSenseError::Config(format!(
+                "The '{}' provider has no default URL. Pass --api-url (e.g. https://<resource>.openai.azure.com) or set OPENAI_API_URL.",
+                kind
+            ))
+            .into())
+        }
+    };
+    let api_key = api_key.filter(|k| !k.is_empty()).map(String::from);
+
+    let provider: Box<dyn LlmProvider> = match kind {
+        ProviderKind::OpenAi | ProviderKind::Azure => {
+            let api_key = api_key.ok_or_else(|| {
+                CommitSenseError::Config(format!(
+                    "An API key is required for the '{}' provider. Pass --api-key or set OPENAI_API_KEY.",
+                    kind
+                ))
+            })?;
+            let mut provider = if kind == ProviderKind::Azure {
+                let deployment = settings.deployment.as_deref().ok_or_else(|| {
+                    CommitSenseError::Config(
+                        "The 'azure' provider needs a deployment: set `deployment` in the [provider] section of the config file."
+                            .to_string(),
+                    )
+                })?;
+                OpenAiCompatibleProvider::azure(api_url, api_key, deployment)
+            } else {
+                if settings.deployment.is_some() {
+                    return Err(CommitSenseError::Config(
+                        "A deployment is only used by the 'azure' provider. Pass --provider azure.".to_string(),
+                    )
+                    .into());
+                }
+                OpenAiCompatibleProvider::new(api_url, api_key)
+            };
+            if let Some(version) = &settings.api_version {
+                provider = provider.with_api_version(version.clone());
+            }
+            if let Some(header) = &settings.auth_header {
+                provider = provider.with_auth_style(AuthStyle::Header(header.clone()));
+            }
+            Box::new(provider.with_headers(header_map(&settings.resolve_headers()?)?))
+        }
+        ProviderKind::Ollama | ProviderKind::LlamaCpp if !settings.is_empty() => {
+            return Err(CommitSenseError::Config(format!(
+                "The [provider] section of the config file only applies to the 'openai' and 'azure' providers, not '{}'.",
+                kind
+            ))
+            .into())
+        }
+        ProviderKind::Ollama => Box::new(OllamaProvider::new(api_url, api_key)),
+        ProviderKind::LlamaCpp => Box::new(LlamaCppProvider::new(api_url, api_key)),
+    };
+    Ok(provider)
+}
+
+/// Builds a header map, rejecting names and values that aren't valid in HTTP.
+fn header_map(headers: &[(String, String)]) -> Result<reqwest::header::HeaderMap> {
+    let mut map = reqwest::header::HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| CommitSenseError::Config(format!("Invalid header name '{}': {}", name, e)))?;
+        let mut header_value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| CommitSenseError::Config(format!("Invalid value for header '{}': {}", name, e)))?;
+        header_value.set_sensitive(true);
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
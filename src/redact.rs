@@ -0,0 +1,137 @@
+use crate::error::CommitSenseError;
+use crate::git::Commit;
+use anyhow::Result;
+use log::{debug, info};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Built-in detectors, applied in this order before any user patterns.
+/// Private key blocks come first so the emails or tokens inside them are counted once.
+const BUILTIN_DETECTORS: &[(&str, &str)] = &[
+    (
+        "private_key",
+        r"(?s)-----BEGIN [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----.*?(-----END [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----|\z)",
+    ),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{5,}\.eyJ[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]*",
+    ),
+    (
+        "github_token",
+        r"\b(gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{22,255})\b",
+    ),
+    (
+        "aws_access_key",
+        r"\b(AKIA|ASIA|ABIA|ACCA|AGPA|AIDA|AIPA|ANPA|ANVA|AROA|APKA)[A-Z0-9]{16}\b",
+    ),
+    (
+        "aws_secret_key",
+        r#"(?i)\baws_?secret_?(access_?)?key["']?\s*[:=]\s*["']?[A-Za-z0-9/+]{40}\b"#,
+    ),
+    (
+        "email",
+        r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+    ),
+];
+
+/// How many values each detector replaced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionReport {
+    /// Number of replaced values, keyed by detector name.
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl RedactionReport {
+    /// Returns the total number of replaced values.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    fn merge(&mut self, other: RedactionReport) {
+        for (name, count) in other.counts {
+            *self.counts.entry(name).or_insert(0) += count;
+        }
+    }
+}
+
+impl fmt::Display for RedactionReport {
+    /// Formats the counts as `name=count, ...` (never the values).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(name, count)| format!("{}={}", name, count))
+            .collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
+
+/// Replaces secrets and personal data with `[REDACTED:<detector>]` before text leaves the machine.
+#[derive(Clone)]
+pub struct Redactor {
+    detectors: Vec<(String, Regex)>,
+}
+
+impl Redactor {
+    /// Builds a redactor from the built-in detectors plus `extra` user patterns, keyed by name.
+    pub fn new(extra: &BTreeMap<String, String>) -> Result<Self> {
+        let mut detectors = BUILTIN_DETECTORS
+            .iter()
+            .map(|(name, pattern)| {
+                let regex = Regex::new(pattern).expect("built-in redaction pattern is valid");
+                (name.to_string(), regex)
+            })
+            .collect::<Vec<_>>();
+        for (name, pattern) in extra {
+            let regex = Regex::new(pattern).map_err(|e| {
+                CommitSenseError::Config(format!("Invalid redaction pattern '{}': {}", name, e))
+            })?;
+            detectors.push((name.clone(), regex));
+        }
+        Ok(Redactor { detectors })
+    }
+
+    /// Redacts `text`, returning the redacted text and how many values each detector replaced.
+    pub fn redact(&self, text: &str) -> (String, RedactionReport) {
+        let mut redacted = text.to_string();
+        let mut report = RedactionReport::default();
+        for (name, regex) in &self.detectors {
+            let count = regex.find_iter(&redacted).count();
+            if count > 0 {
+                let replacement = format!("[REDACTED:{}]", name);
+                redacted = regex.replace_all(&redacted, replacement.as_str()).into_owned();
+                report.counts.insert(name.clone(), count);
+            }
+        }
+        (redacted, report)
+    }
+
+    /// Redacts the messages of `commits` and their attached changes in place and logs the counts.
+    pub fn redact_commits(&self, commits: &mut [Commit]) -> RedactionReport {
+        let mut report = RedactionReport::default();
+        for commit in commits.iter_mut() {
+            let (message, mut commit_report) = self.redact(&commit.message);
+            commit.message = message;
+            if let Some(changes) = &commit.changes {
+                let (changes, changes_report) = self.redact(changes);
+                commit.changes = Some(changes);
+                commit_report.merge(changes_report);
+            }
+            if commit_report.total() > 0 {
+                debug!("Redacted commit {}: {}", commit.short_oid(), commit_report);
+                report.merge(commit_report);
+            }
+        }
+        if report.total() > 0 {
+            info!(
+                "Redacted {} value(s) from the commits before analysis: {}",
+                report.total(),
+                report
+            );
+        } else {
+            debug!("Nothing to redact in {} commits.", commits.len());
+        }
+        report
+    }
+}
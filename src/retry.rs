@@ -0,0 +1,137 @@
+use crate::error::CommitSenseError;
+use crate::provider::{CompletionRequest, CompletionResponse, LlmProvider};
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Controls how transient API failures (rate limits, timeouts, server and connection errors) are retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every subsequent retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for any delay. A server asking to wait longer with `Retry-After` isn't retried.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the exponential backoff before retry number `retry` (starting at 1), without jitter.
+    pub fn base_delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Returns the delay before retry number `retry` for the given error, or `None` if the server's
+    /// `Retry-After` (sent with rate limits and server errors) is longer than `max_backoff`.
+    /// Uses the `Retry-After` if present, otherwise the backoff with "equal jitter"
+    /// (a random delay between half and all of the base delay) so parallel jobs don't retry in lockstep.
+    pub fn delay_for(&self, retry: u32, error: &CommitSenseError) -> Option<Duration> {
+        if let CommitSenseError::ApiRateLimit {
+            retry_after: Some(retry_after),
+            ..
+        }
+        | CommitSenseError::ApiServer {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return (*retry_after <= self.max_backoff).then_some(*retry_after);
+        }
+        let base = self.base_delay(retry);
+        Some(base / 2 + base.mul_f64(random_fraction() / 2.0))
+    }
+}
+
+/// Returns a pseudo-random number in `[0, 1)`.
+/// Good enough for jitter, and avoids pulling in a dependency just for this.
+fn random_fraction() -> f64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Wraps another provider and retries transient failures according to a `RetryPolicy`.
+pub struct RetryingProvider {
+    inner: Box<dyn LlmProvider>,
+    policy: RetryPolicy,
+}
+
+impl RetryingProvider {
+    /// Creates a provider that retries calls to `inner`.
+    pub fn new(inner: Box<dyn LlmProvider>, policy: RetryPolicy) -> Self {
+        RetryingProvider { inner, policy }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RetryingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let mut retry = 0;
+        loop {
+            let error = match self.inner.complete(request).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+
+            let classified = error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<CommitSenseError>())
+                .filter(|e| e.is_retryable());
+            let Some(classified) = classified else {
+                return Err(error);
+            };
+            if retry >= self.policy.max_retries {
+                return Err(error.context(format!(
+                    "Giving up after {} retr{}",
+                    retry,
+                    if retry == 1 { "y" } else { "ies" }
+                )));
+            }
+
+            // Waiting longer than the backoff cap would only run into the overall time limit
+            let Some(delay) = self.policy.delay_for(retry + 1, classified) else {
+                return Err(error.context(format!(
+                    "Not retrying: the server asked to wait longer than {}s",
+                    self.policy.max_backoff.as_secs()
+                )));
+            };
+            retry += 1;
+            warn!(
+                "{} (retry {}/{} in {:.1}s)",
+                classified,
+                retry,
+                self.policy.max_retries,
+                delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
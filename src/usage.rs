@@ -0,0 +1,160 @@
+use crate::provider::{CompletionRequest, CompletionResponse, LlmProvider, TokenUsage};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// List prices in USD per million tokens (input, output) by model name prefix, at the time of writing.
+/// More specific prefixes come first. Configure `input_cost_per_million` / `output_cost_per_million`
+/// for a model to override these or to price models not listed here.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4-32k", 60.00, 120.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o1-mini", 1.10, 4.40),
+    ("o1", 15.00, 60.00),
+    ("o3-mini", 1.10, 4.40),
+    ("o3", 2.00, 8.00),
+    ("o4-mini", 1.10, 4.40),
+];
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    /// Price of a million prompt tokens.
+    pub input_per_million: f64,
+    /// Price of a million completion tokens.
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Returns the price of `model`, applying any configured overrides.
+    /// Returns `None` if the model is unknown and no price is configured.
+    pub fn for_model(model: &str, input_per_million: Option<f64>, output_per_million: Option<f64>) -> Option<Self> {
+        let builtin = PRICES.iter().find(|(prefix, _, _)| model.starts_with(prefix));
+        Some(ModelPrice {
+            input_per_million: input_per_million.or(builtin.map(|(_, input, _)| *input))?,
+            output_per_million: output_per_million.or(builtin.map(|(_, _, output)| *output))?,
+        })
+    }
+
+    /// Returns the cost of `usage` in USD.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_million
+            + usage.completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Requests and tokens spent during a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunUsage {
+    /// Number of successful completion requests.
+    pub requests: u64,
+    /// Tokens summed over the requests that reported usage.
+    #[serde(flatten)]
+    pub tokens: TokenUsage,
+    /// Requests whose response carried no token counts, so the totals are a lower bound.
+    pub unreported_requests: u64,
+}
+
+impl std::ops::AddAssign for RunUsage {
+    fn add_assign(&mut self, other: RunUsage) {
+        self.requests += other.requests;
+        self.tokens += other.tokens;
+        self.unreported_requests += other.unreported_requests;
+    }
+}
+
+/// Collects the usage of every request sent through a `MeteredProvider`. Clones share the totals.
+#[derive(Debug, Clone, Default)]
+pub struct UsageMeter {
+    totals: Arc<Mutex<RunUsage>>,
+}
+
+impl UsageMeter {
+    /// Returns the usage recorded so far.
+    pub fn totals(&self) -> RunUsage {
+        *self.totals.lock().expect("usage meter lock is not poisoned")
+    }
+
+    fn record(&self, usage: Option<TokenUsage>) {
+        let mut totals = self.totals.lock().expect("usage meter lock is not poisoned");
+        totals.requests += 1;
+        match usage {
+            Some(usage) => totals.tokens += usage,
+            None => totals.unreported_requests += 1,
+        }
+    }
+}
+
+/// Wraps another provider and records the token usage of every successful request.
+pub struct MeteredProvider {
+    inner: Box<dyn LlmProvider>,
+    meter: UsageMeter,
+}
+
+impl MeteredProvider {
+    /// Creates a provider that records the usage of calls to `inner` in `meter`.
+    pub fn new(inner: Box<dyn LlmProvider>, meter: UsageMeter) -> Self {
+        MeteredProvider { inner, meter }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MeteredProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse> {
+        let response = self.inner.complete(request).await?;
+        self.meter.record(response.usage);
+        Ok(response)
+    }
+}
+
+/// One line of the JSONL usage log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    /// When the run finished, in RFC 3339 format.
+    pub timestamp: String,
+    /// The repository the run analyzed (`GITHUB_REPOSITORY` in Actions, otherwise the project path).
+    pub repository: String,
+    /// The provider requests were sent to.
+    pub provider: String,
+    /// The model requests were sent with.
+    pub model: String,
+    /// Requests and tokens spent.
+    #[serde(flatten)]
+    pub usage: RunUsage,
+    /// Sum of prompt and completion tokens.
+    pub total_tokens: u64,
+    /// Estimated cost in USD, or `None` if the model has no known price.
+    pub estimated_cost: Option<f64>,
+}
+
+/// Appends `record` as one JSON line to the usage log at `path`, creating the file if needed.
+pub fn append_usage_log(path: &Path, record: &UsageRecord) -> Result<()> {
+    let line = serde_json::to_string(record).context("Failed to serialize usage record")?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open usage log '{}'", path.display()))?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write usage log '{}'", path.display()))?;
+    Ok(())
+}
@@ -1,5 +1,52 @@
 use semver::Version;
 use chrono::Utc;
+use std::str::FromStr;
+
+/// The kinds of version bump CommitSense can suggest, ordered from least to most significant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BumpType {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl BumpType {
+    /// Returns the lowercase name used in AI responses and GitHub outputs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BumpType::None => "none",
+            BumpType::Patch => "patch",
+            BumpType::Minor => "minor",
+            BumpType::Major => "major",
+        }
+    }
+}
+
+/// Parses a bump type case-insensitively ("major", "minor", "patch", "none").
+impl FromStr for BumpType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(BumpType::None),
+            "patch" => Ok(BumpType::Patch),
+            "minor" => Ok(BumpType::Minor),
+            "major" => Ok(BumpType::Major),
+            _ => Err(format!(
+                "Invalid bump type '{}'. Expected 'major', 'minor', 'patch', or 'none'.",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for BumpType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 /// Calculates the expected next version based on a strict interpretation of the bump type.
 ///
@@ -38,7 +85,7 @@ pub fn calculate_expected_version(current_version: &Version, bump_type: &str) ->
             expected_version.pre = semver::Prerelease::EMPTY;
             expected_version.build = semver::BuildMetadata::EMPTY;
         }
-        "none" | _ => {
+        _ => {
             // If "none" or an unexpected bump type is provided (though it should be validated earlier),
             // the expected version is simply the current version.
             // No changes needed, expected_version is already a clone.
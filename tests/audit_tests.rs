@@ -0,0 +1,128 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use commit_sense::audit::*;
+use commit_sense::openai::AISuggestion;
+use commit_sense::provider::*;
+use commit_sense::CommitSenseError;
+use std::fs;
+use tempfile::tempdir;
+
+fn record(base_oid: &str) -> AuditRecord {
+    AuditRecord {
+        timestamp: "2025-04-10T12:00:00+00:00".to_string(),
+        repository: "acme/widgets".to_string(),
+        base_oid: base_oid.to_string(),
+        head_oid: "b".repeat(40),
+        model: "gpt-4o".to_string(),
+        exchanges: vec![Exchange {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            messages: vec![ChatMessage::user("Commits:\n- fix: crash")],
+            response: Some(r#"{"bump": "patch"}"#.to_string()),
+            error: None,
+        }],
+        suggestion: Some(AISuggestion {
+            bump_type: "patch".to_string(),
+            next_version: "1.0.1".to_string(),
+            changelog_markdown: "- Fix crash".to_string(),
+            confidence: Some(0.8),
+            ..Default::default()
+        }),
+        error: None,
+        prev_hash: String::new(),
+        hash: String::new(),
+    }
+}
+
+#[test]
+fn test_records_are_hash_chained() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("audit.jsonl");
+
+    let first = append_audit_record(&path, record("a1"))?;
+    let second = append_audit_record(&path, record("a2"))?;
+
+    let lines: Vec<serde_json::Value> = fs::read_to_string(&path)?
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["prev_hash"], GENESIS_HASH);
+    assert_eq!(lines[0]["hash"], first.as_str());
+    assert_eq!(lines[1]["prev_hash"], first.as_str());
+    assert_eq!(lines[0]["exchanges"][0]["messages"][0]["content"], "Commits:\n- fix: crash");
+    assert_eq!(lines[0]["suggestion"]["next_version"], "1.0.1");
+    assert_eq!(verify_audit_log(&path)?, (2, second));
+
+    Ok(())
+}
+
+#[test]
+fn test_tampering_is_detected() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("audit.jsonl");
+    for base in ["a1", "a2", "a3"] {
+        append_audit_record(&path, record(base))?;
+    }
+    let original = fs::read_to_string(&path)?;
+    let lines: Vec<&str> = original.lines().collect();
+
+    let edited = original.replacen("\"next_version\":\"1.0.1\"", "\"next_version\":\"2.0.0\"", 1);
+    let removed = format!("{}\n{}\n", lines[0], lines[2]);
+    let reordered = format!("{}\n{}\n{}\n", lines[1], lines[0], lines[2]);
+    for (tampered, reason) in [
+        (edited, "line 1: its content doesn't match its hash"),
+        (removed, "line 2: it doesn't follow the previous record"),
+        (reordered, "line 1: it doesn't follow the previous record"),
+    ] {
+        fs::write(&path, tampered)?;
+        let err = verify_audit_log(&path).unwrap_err();
+        assert!(err.to_string().contains(reason), "{}: {}", reason, err);
+    }
+
+    // A log whose last record is unreadable can't be extended
+    fs::write(&path, format!("{}\nnot json\n", lines[0]))?;
+    let err = append_audit_record(&path, record("a4")).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(CommitSenseError::Config(_))));
+
+    Ok(())
+}
+
+/// Succeeds on the first request and fails on the others.
+struct FlakyProvider(std::sync::atomic::AtomicUsize);
+
+#[async_trait]
+impl LlmProvider for FlakyProvider {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        match self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+            0 => Ok(CompletionResponse { content: "first".to_string(), usage: None }),
+            _ => Err(CommitSenseError::ApiServer { status: 503, message: "Service Unavailable".to_string(), retry_after: None }.into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_auditing_provider_records_every_exchange() {
+    let trail = AuditTrail::default();
+    let provider = AuditingProvider::new(Box::new(FlakyProvider(Default::default())), trail.clone());
+    let request = CompletionRequest {
+        model: "gpt-4o".to_string(),
+        messages: vec![ChatMessage::system("You version releases."), ChatMessage::user("- fix: crash")],
+        ..Default::default()
+    };
+
+    provider.complete(&request).await.unwrap();
+    assert!(provider.complete(&request).await.is_err());
+
+    let exchanges = trail.exchanges();
+    assert_eq!(exchanges.len(), 2);
+    assert_eq!(exchanges[0].provider, "flaky");
+    assert_eq!(exchanges[0].messages, request.messages);
+    assert_eq!(exchanges[0].response.as_deref(), Some("first"));
+    assert_eq!(exchanges[1].response, None);
+    assert!(exchanges[1].error.as_deref().unwrap().contains("503"));
+}
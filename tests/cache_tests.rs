@@ -0,0 +1,56 @@
+use anyhow::Result;
+use commit_sense::cache::*;
+use commit_sense::openai::AISuggestion;
+use std::fs;
+use tempfile::tempdir;
+
+fn oids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_cache_key_covers_all_inputs() {
+    let key = CacheKey::new("openai default", "gpt-4o", "template", "1.0.0", &oids(&["a", "b"]));
+    assert_eq!(key, CacheKey::new("openai default", "gpt-4o", "template", "1.0.0", &oids(&["a", "b"])));
+    assert_eq!(key.as_str().len(), 64);
+
+    assert_ne!(key, CacheKey::new("azure https://example.openai.azure.com prod", "gpt-4o", "template", "1.0.0", &oids(&["a", "b"])));
+    assert_ne!(key, CacheKey::new("openai default", "gpt-4o-mini", "template", "1.0.0", &oids(&["a", "b"])));
+    assert_ne!(key, CacheKey::new("openai default", "gpt-4o", "other template", "1.0.0", &oids(&["a", "b"])));
+    assert_ne!(key, CacheKey::new("openai default", "gpt-4o", "template", "1.0.1", &oids(&["a", "b"])));
+    // Commit order matters
+    assert_ne!(key, CacheKey::new("openai default", "gpt-4o", "template", "1.0.0", &oids(&["b", "a"])));
+    // Field boundaries are unambiguous
+    assert_ne!(
+        CacheKey::new("openai default", "ab", "c", "1.0.0", &[]),
+        CacheKey::new("openai default", "a", "bc", "1.0.0", &[])
+    );
+}
+
+#[test]
+fn test_cache_round_trip() -> Result<()> {
+    let dir = tempdir()?;
+    let cache = ResponseCache::new(dir.path().join("cache"));
+    let key = CacheKey::new("openai default", "gpt-4o", "template", "1.0.0", &oids(&["a"]));
+
+    assert!(cache.get(&key).is_none());
+
+    let suggestion = AISuggestion {
+        bump_type: "minor".to_string(),
+        next_version: "1.1.0".to_string(),
+        changelog_markdown: "- Add search".to_string(),
+        ..Default::default()
+    };
+    cache.put(&key, &suggestion)?;
+
+    let cached = cache.get(&key).expect("entry was written");
+    assert_eq!(cached.bump_type, "minor");
+    assert_eq!(cached.next_version, "1.1.0");
+    assert_eq!(cached.changelog_markdown, "- Add search");
+
+    // Corrupt entries are treated as misses
+    fs::write(dir.path().join("cache").join(format!("{}.json", key.as_str())), "{not json")?;
+    assert!(cache.get(&key).is_none());
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use commit_sense::cassette::*;
+use commit_sense::openai::OpenAIClient;
+use commit_sense::provider::*;
+use commit_sense::ProjectType;
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+/// Answers with the given replies in order, like a live model.
+struct LiveProvider {
+    replies: Mutex<Vec<&'static str>>,
+}
+
+impl LiveProvider {
+    fn new(replies: Vec<&'static str>) -> Self {
+        LiveProvider {
+            replies: Mutex::new(replies.into_iter().rev().collect()),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for LiveProvider {
+    fn name(&self) -> &str {
+        "live"
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        let reply = self.replies.lock().unwrap().pop().expect("no reply left");
+        Ok(CompletionResponse {
+            content: reply.to_string(),
+            usage: Some(TokenUsage { prompt_tokens: 100, completion_tokens: 20 }),
+        })
+    }
+}
+
+fn request(content: &str) -> CompletionRequest {
+    CompletionRequest {
+        model: "gpt-4o".to_string(),
+        messages: vec![ChatMessage::system("You version releases."), ChatMessage::user(content)],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_key_ignores_incidental_formatting() {
+    let key = NormalizedRequest::new(&request("Commits:\n- fix: crash")).key();
+
+    assert_eq!(NormalizedRequest::new(&request("Commits:  \r\n- fix: crash\n\n")).key(), key);
+    assert_ne!(NormalizedRequest::new(&request("Commits:\n- feat: search")).key(), key);
+    let mut other_model = request("Commits:\n- fix: crash");
+    other_model.model = "gpt-4o-mini".to_string();
+    assert_ne!(NormalizedRequest::new(&other_model).key(), key);
+    let mut seeded = request("Commits:\n- fix: crash");
+    seeded.seed = Some(1);
+    assert_ne!(NormalizedRequest::new(&seeded).key(), key);
+}
+
+#[tokio::test]
+async fn test_recorded_run_is_replayed_without_network() -> Result<()> {
+    let dir = tempdir()?;
+    let commits = vec!["fix: crash on empty input".to_string()];
+    // Three samples send the same request three times
+    let live = LiveProvider::new(vec![
+        r#"{"bump": "patch", "next_version": "1.0.1", "changelog": "- Fix crash"}"#,
+        r#"{"bump": "minor", "next_version": "1.1.0", "changelog": "- Fix crash"}"#,
+        r#"{"bump": "patch", "next_version": "1.0.1", "changelog": "- Fix crash"}"#,
+    ]);
+    let recorder = RecordingProvider::new(Box::new(live), dir.path());
+    let client = OpenAIClient::with_provider(Box::new(recorder), "gpt-4o".to_string()).with_samples(3);
+    let recorded = client.get_version_and_changelog("1.0.0", &commits, ProjectType::Rust).await?;
+
+    let fixtures: Vec<_> = std::fs::read_dir(dir.path())?.collect();
+    assert_eq!(fixtures.len(), 1);
+    let fixture: Fixture = serde_json::from_str(&std::fs::read_to_string(fixtures[0].as_ref().unwrap().path())?)?;
+    assert_eq!(fixture.responses.len(), 3);
+    assert_eq!(fixture.request.model, "gpt-4o");
+
+    let replay = ReplayProvider::new(dir.path());
+    let client = OpenAIClient::with_provider(Box::new(replay), "gpt-4o".to_string()).with_samples(3);
+    let replayed = client.get_version_and_changelog("1.0.0", &commits, ProjectType::Rust).await?;
+
+    assert_eq!(replayed.bump_type, recorded.bump_type);
+    assert_eq!(replayed.vote.as_ref().unwrap().votes, recorded.vote.as_ref().unwrap().votes);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_miss_is_an_error() -> Result<()> {
+    let dir = tempdir()?;
+    let replay = ReplayProvider::new(dir.path());
+
+    let err = replay.complete(&request("Commits:\n- fix: crash")).await.unwrap_err();
+
+    assert!(matches!(err.downcast_ref::<commit_sense::CommitSenseError>(), Some(commit_sense::CommitSenseError::Config(_))));
+    assert!(err.to_string().contains("Re-record the cassette"));
+    assert!(!is_api_error(&err));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rerecording_replaces_stale_responses() -> Result<()> {
+    let dir = tempdir()?;
+    let first = RecordingProvider::new(Box::new(LiveProvider::new(vec!["old"])), dir.path());
+    first.complete(&request("hi")).await?;
+    let second = RecordingProvider::new(Box::new(LiveProvider::new(vec!["new"])), dir.path());
+    second.complete(&request("hi")).await?;
+
+    let replay = ReplayProvider::new(dir.path());
+    let response = replay.complete(&request("hi")).await?;
+    assert_eq!(response.content, "new");
+    assert_eq!(response.usage, Some(TokenUsage { prompt_tokens: 100, completion_tokens: 20 }));
+    // Only one response was recorded in the latest run
+    assert!(replay.complete(&request("hi")).await.is_err());
+
+    Ok(())
+}
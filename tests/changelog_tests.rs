@@ -100,3 +100,35 @@ fn test_format_and_write() -> Result<()> {
     
     Ok(())
 }
+
+#[test]
+fn test_localized_changelogs() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().to_path_buf();
+
+    write_localized_changelog(&path, "de", "## [1.1.0] - 2025-01-01\n\n- Suche hinzugefügt")?;
+    write_localized_changelog(&path, "ja", "## [1.1.0] - 2025-01-01\n\n- 検索を追加")?;
+
+    assert!(fs::read_to_string(path.join("CHANGELOG.de.md"))?.contains("- Suche hinzugefügt"));
+    assert!(fs::read_to_string(path.join("CHANGELOG.ja.md"))?.contains("- 検索を追加"));
+    // The default changelog is left alone
+    assert!(!path.join("CHANGELOG.md").exists());
+
+    assert_eq!(localized_changelog_file("pt-BR"), "CHANGELOG.pt-BR.md");
+    assert!(is_source_language("en"));
+    assert!(is_source_language("en-GB"));
+    assert!(!is_source_language("de"));
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_language() {
+    use commit_sense::cli::parse_language;
+
+    assert_eq!(parse_language("de"), Ok("de".to_string()));
+    assert_eq!(parse_language("pt-BR"), Ok("pt-BR".to_string()));
+    assert!(parse_language("").is_err());
+    assert!(parse_language("german").is_err());
+    assert!(parse_language("../evil").is_err());
+}
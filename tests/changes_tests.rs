@@ -0,0 +1,86 @@
+use anyhow::Result;
+use commit_sense::changes::*;
+use commit_sense::git::{self, FileChange};
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn git_in(path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(path).output()?;
+    assert!(output.status.success(), "git {:?} failed", args);
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+fn commit_files(path: &Path, files: &[(&str, &str)], message: &str) -> Result<String> {
+    for (name, content) in files {
+        let file = path.join(name);
+        std::fs::create_dir_all(file.parent().unwrap())?;
+        std::fs::write(file, content)?;
+        git_in(path, &["add", name])?;
+    }
+    git_in(path, &["commit", "-m", message])?;
+    git_in(path, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn test_file_filter() -> Result<()> {
+    let filter = FileFilter::new(&["internal/**".to_string()])?;
+    assert!(filter.is_excluded("Cargo.lock"));
+    assert!(filter.is_excluded("packages/web/package-lock.json"));
+    assert!(filter.is_excluded("static/app.min.js"));
+    assert!(filter.is_excluded("src/generated/schema.rs"));
+    assert!(filter.is_excluded("internal/cache.rs"));
+    assert!(!filter.is_excluded("src/lib.rs"));
+    assert!(!filter.is_excluded("tests/api_tests.rs"));
+
+    assert!(FileFilter::new(&["[".to_string()]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_render_changes() {
+    let changes = CommitChanges {
+        files: vec![
+            FileChange { path: "src/api.rs".to_string(), additions: Some(10), deletions: Some(2) },
+            FileChange { path: "logo.png".to_string(), additions: None, deletions: None },
+        ],
+        excluded_files: 1,
+        diff: Some("diff --git a/src/api.rs b/src/api.rs\n".to_string()),
+    };
+    assert_eq!(
+        changes.render(),
+        "Changed files (+added -deleted):\n  src/api.rs (+10 -2)\n  logo.png (binary)\n  (1 lockfile(s) or generated file(s) omitted)\nDiff:\n```diff\ndiff --git a/src/api.rs b/src/api.rs\n```"
+    );
+}
+
+#[test]
+fn test_collect_filters_lockfiles_and_respects_budget() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path();
+    git_in(path, &["init"])?;
+    git_in(path, &["config", "user.name", "Test User"])?;
+    git_in(path, &["config", "user.email", "test@foxycorps.com"])?;
+    let base = commit_files(path, &[("README.md", "hello\n")], "Initial commit")?;
+
+    let small = commit_files(path, &[("src/api.rs", "pub fn api() {}\n"), ("Cargo.lock", "lock\n")], "misc fixes")?;
+    let large = commit_files(path, &[("tests/big.rs", &"// line\n".repeat(600))], "wip")?;
+    let oids: Vec<String> = git::get_commits_since_oid(path, &base)?.into_iter().map(|c| c.oid).collect();
+    assert_eq!(oids, vec![small.clone(), large.clone()]);
+
+    let all = collect(path, &oids, &ChangeContextOptions::default())?;
+    assert_eq!(all[0].files.len(), 1);
+    assert_eq!(all[0].files[0].path, "src/api.rs");
+    assert_eq!(all[0].excluded_files, 1);
+    let diff = all[0].diff.as_ref().expect("small diff is attached");
+    assert!(diff.contains("+pub fn api() {}"));
+    assert!(!diff.contains("Cargo.lock"));
+    // The big commit only lists its files
+    assert_eq!(all[1].files[0].additions, Some(600));
+    assert!(all[1].diff.is_none());
+
+    // An exhausted budget leaves out every diff
+    let options = ChangeContextOptions { diff_budget: 10, ..Default::default() };
+    assert!(collect(path, &oids, &options)?.iter().all(|c| c.diff.is_none()));
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use commit_sense::chunking::*;
+use commit_sense::provider::ChatMessage;
+
+#[test]
+fn test_estimate_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("abcd"), 1);
+    assert_eq!(estimate_tokens("abcde"), 2);
+    // Counted in characters, not bytes
+    assert_eq!(estimate_tokens("ééééé"), 2);
+
+    let messages = vec![ChatMessage::system("abcd"), ChatMessage::user("abcdefgh")];
+    assert_eq!(estimate_message_tokens(&messages), 3 + 2 * 4);
+}
+
+#[test]
+fn test_chunk_by_tokens_respects_budget_and_order() {
+    let items = [3, 4, 2, 5, 1];
+    let batches = chunk_by_tokens(&items, 7, |n| *n);
+    assert_eq!(batches, vec![&[3, 4][..], &[2, 5][..], &[1][..]]);
+
+    // Oversized items get a batch of their own
+    let batches = chunk_by_tokens(&[10, 1, 1], 5, |n| *n);
+    assert_eq!(batches, vec![&[10][..], &[1, 1][..]]);
+
+    assert!(chunk_by_tokens(&[] as &[usize], 5, |n| *n).is_empty());
+}
+
+#[test]
+fn test_chunk_settings_for_model() {
+    let settings = ChunkSettings::for_model("gpt-4o-mini", None, None);
+    assert_eq!(settings.context_tokens, 128_000);
+    assert_eq!(settings.chunk_tokens, 32_000);
+    assert_eq!(settings.prompt_budget(), 128_000 - RESPONSE_RESERVE_TOKENS);
+
+    let settings = ChunkSettings::for_model("my-local-model", None, None);
+    assert_eq!(settings.context_tokens, DEFAULT_CONTEXT_TOKENS);
+
+    let settings = ChunkSettings::for_model("gpt-4o", Some(16_000), Some(2_000));
+    assert_eq!(settings, ChunkSettings { context_tokens: 16_000, chunk_tokens: 2_000 });
+}
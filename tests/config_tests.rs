@@ -0,0 +1,100 @@
+use anyhow::Result;
+use commit_sense::cli::ProviderKind;
+use commit_sense::config::Config;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_config_model_settings() -> Result<()> {
+    let dir = tempdir()?;
+    let path = Config::default_path(dir.path());
+
+    // A missing optional file means defaults; a missing explicit file is an error
+    assert!(Config::load(&path, false)?.models.is_empty());
+    assert!(Config::load(&path, true).is_err());
+
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(
+        &path,
+        "[models.\"llama3.1:8b\"]\ncontext_tokens = 32000\nchunk_tokens = 4000\n",
+    )?;
+    let config = Config::load(&path, true)?;
+    let model = config.model("llama3.1:8b");
+    assert_eq!(model.context_tokens, Some(32_000));
+    assert_eq!(model.chunk_tokens, Some(4_000));
+    assert_eq!(config.model("gpt-4o").context_tokens, None);
+
+    // Typos are rejected rather than silently ignored
+    fs::write(&path, "[models.m]\ncontext_token = 1\n")?;
+    assert!(Config::load(&path, true).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_config_notes_profiles() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("config.toml");
+
+    fs::write(
+        &path,
+        "[notes.customers]\ninstructions = \"Plain-language highlights.\"\n\n[notes.developers]\ninstructions = \"API-level detail.\"\nfile = \"docs/DEVELOPER_NOTES.md\"\n",
+    )?;
+    let config = Config::load(&path, true)?;
+    assert_eq!(config.notes.keys().collect::<Vec<_>>(), vec!["customers", "developers"]);
+    assert_eq!(config.notes["customers"].file("customers"), std::path::PathBuf::from("RELEASE_NOTES.customers.md"));
+    assert_eq!(config.notes["developers"].file("developers"), std::path::PathBuf::from("docs/DEVELOPER_NOTES.md"));
+
+    // Profiles need instructions, and names end up in file and output names
+    fs::write(&path, "[notes.customers]\nfile = \"NOTES.md\"\n")?;
+    assert!(Config::load(&path, true).is_err());
+    fs::write(&path, "[notes.\"end users\"]\ninstructions = \"x\"\n")?;
+    assert!(Config::load(&path, true).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_config_provider_section() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("config.toml");
+
+    fs::write(
+        &path,
+        "[provider]\ndeployment = \"prod-gpt4o\"\napi_version = \"2024-10-21\"\n\n[provider.headers]\n\"X-Team\" = \"release\"\n",
+    )?;
+    let config = Config::load(&path, true)?;
+    assert_eq!(config.provider.deployment.as_deref(), Some("prod-gpt4o"));
+    assert_eq!(config.provider.resolve_headers()?, vec![("X-Team".to_string(), "release".to_string())]);
+    assert!(Config::default().provider.is_empty());
+
+    fs::write(&path, "[provider]\nendpoint = \"https://example.com\"\n")?;
+    assert!(Config::load(&path, true).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_config_fallback_chain() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("config.toml");
+
+    fs::write(
+        &path,
+        "[models.gpt-4o]\ntemperature = 0.2\nseed = 1\n\n[[fallback]]\nmodel = \"gpt-4o-mini\"\ntemperature = 0.0\nmax_tokens = 1500\n\n[[fallback]]\nmodel = \"llama3\"\nprovider = \"ollama\"\n",
+    )?;
+    let config = Config::load(&path, true)?;
+    assert_eq!(config.model("gpt-4o").temperature, Some(0.2));
+    assert_eq!(config.fallback.len(), 2);
+    assert_eq!(config.fallback[0].model, "gpt-4o-mini");
+    assert_eq!(config.fallback[0].max_tokens, Some(1500));
+    assert_eq!(config.fallback[0].provider_kind(ProviderKind::OpenAi)?, ProviderKind::OpenAi);
+    assert_eq!(config.fallback[1].provider_kind(ProviderKind::OpenAi)?, ProviderKind::Ollama);
+
+    fs::write(&path, "[[fallback]]\nmodel = \"llama3\"\nprovider = \"bard\"\n")?;
+    assert!(Config::load(&path, true).is_err());
+    fs::write(&path, "[[fallback]]\ntemperature = 0.5\n")?;
+    assert!(Config::load(&path, true).is_err());
+
+    Ok(())
+}
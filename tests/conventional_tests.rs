@@ -0,0 +1,118 @@
+use anyhow::Result;
+use commit_sense::conventional::*;
+use commit_sense::git::Commit;
+use commit_sense::openai::CommitClassification;
+use commit_sense::version::BumpType;
+
+#[test]
+fn test_parse_commit_with_scope_and_bang() {
+    let commit = parse_commit("feat(api)!: Drop legacy endpoint").unwrap();
+
+    assert_eq!(commit.commit_type, "feat");
+    assert_eq!(commit.scope.as_deref(), Some("api"));
+    assert!(commit.breaking);
+    assert_eq!(commit.description, "Drop legacy endpoint");
+    assert_eq!(commit.bump(), BumpType::Major);
+}
+
+#[test]
+fn test_parse_commit_breaking_footer() {
+    let commit = parse_commit("fix: Tighten validation\n\nBREAKING CHANGE: empty names are now rejected").unwrap();
+
+    assert!(commit.breaking);
+    assert_eq!(commit.breaking_note.as_deref(), Some("empty names are now rejected"));
+    assert_eq!(commit.bump(), BumpType::Major);
+}
+
+#[test]
+fn test_parse_commit_non_conventional() {
+    assert!(parse_commit("Update README").is_none());
+    assert!(parse_commit("wip").is_none());
+}
+
+#[test]
+fn test_required_bump() {
+    let commits = vec![
+        "docs: Update README".to_string(),
+        "fix: Correct typo".to_string(),
+        "feat: Add search".to_string(),
+        "misc cleanup".to_string(),
+    ];
+    assert_eq!(required_bump(&commits), BumpType::Minor);
+    assert_eq!(required_bump(&commits[..1]), BumpType::None);
+}
+
+#[test]
+fn test_analyze_builds_suggestion_and_changelog() -> Result<()> {
+    let commits = vec![
+        "feat(cli): add --verbose flag".to_string(),
+        "fix: handle missing config".to_string(),
+        "chore: bump deps".to_string(),
+    ];
+
+    let suggestion = analyze("1.4.2", &commits)?;
+
+    assert_eq!(suggestion.bump_type, "minor");
+    assert_eq!(suggestion.next_version, "1.5.0");
+    assert!(suggestion.changelog_markdown.contains("### Features\n\n- **cli:** Add --verbose flag"));
+    assert!(suggestion.changelog_markdown.contains("### Bug Fixes\n\n- Handle missing config"));
+    assert!(!suggestion.changelog_markdown.contains("bump deps"));
+    assert_eq!(suggestion.rationale, "minor because of: feat(cli): add --verbose flag");
+    assert_eq!(suggestion.confidence, None);
+    Ok(())
+}
+
+#[test]
+fn test_analyze_no_relevant_commits() -> Result<()> {
+    let commits = vec!["ci: tweak workflow".to_string()];
+
+    let suggestion = analyze("0.3.0", &commits)?;
+
+    assert_eq!(suggestion.bump_type, "none");
+    assert_eq!(suggestion.next_version, "0.3.0");
+    Ok(())
+}
+
+#[test]
+fn test_classify_commits() {
+    let feature = classify(&Commit::new("a1", "feat(cli)!: drop --legacy\n\nBREAKING CHANGE: remove the --legacy flag"));
+    assert_eq!(feature.commit_type, "feat");
+    assert_eq!(feature.scope.as_deref(), Some("cli"));
+    assert!(feature.breaking);
+    assert!(feature.user_facing);
+    assert_eq!(feature.changelog_line.as_deref(), Some("Remove the --legacy flag"));
+    assert_eq!(feature.source, "conventional");
+
+    let chore = classify(&Commit::new("b2", "chore: bump deps"));
+    assert!(!chore.user_facing);
+    assert_eq!(chore.changelog_line, None);
+
+    let free_form = classify(&Commit::new("c3", "Update the README"));
+    assert_eq!(free_form.commit_type, "other");
+    assert!(!free_form.user_facing);
+}
+
+#[test]
+fn test_fill_classifications_covers_every_commit_in_order() {
+    let commits = vec![
+        Commit::new("a1", "fix: handle missing config"),
+        Commit::new("b2", "Rework the parser"),
+    ];
+    let from_model = CommitClassification {
+        oid: "b2".to_string(),
+        commit_type: "refactor".to_string(),
+        scope: Some("parser".to_string()),
+        breaking: false,
+        user_facing: false,
+        changelog_line: None,
+        source: "model".to_string(),
+    };
+
+    let filled = fill_classifications(vec![from_model.clone()], &commits);
+
+    assert_eq!(filled.len(), 2);
+    assert_eq!(filled[0].oid, "a1");
+    assert_eq!(filled[0].source, "conventional");
+    assert_eq!(filled[0].changelog_line.as_deref(), Some("Handle missing config"));
+    assert_eq!(filled[1], from_model);
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use commit_sense::cassette::{RecordingProvider, ReplayProvider};
+use commit_sense::eval::*;
+use commit_sense::openai::{AISuggestion, OpenAIClient};
+use commit_sense::provider::*;
+use commit_sense::version::BumpType;
+use commit_sense::{CommitSenseError, ProjectType};
+use std::fs;
+use tempfile::tempdir;
+
+fn case(name: &str, expected_bump: BumpType) -> EvalCase {
+    EvalCase {
+        name: name.to_string(),
+        current_version: "1.0.0".to_string(),
+        commits: vec!["fix: crash".to_string()],
+        expected_bump,
+        reference_changelog: None,
+    }
+}
+
+fn suggestion(bump: &str) -> AISuggestion {
+    AISuggestion {
+        bump_type: bump.to_string(),
+        next_version: "1.1.0".to_string(),
+        changelog_markdown: "### Features\n- Add search".to_string(),
+        rationale: "test".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_load_dataset_reads_json_lines() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("cases.jsonl");
+    fs::write(
+        &path,
+        r#"{"name": "search", "commits": ["feat: add search"], "expected_bump": "minor", "reference_changelog": "- Add search"}
+
+{"name": "api", "current_version": "2.3.1", "commits": ["refactor!: drop v1 API", "docs: update"], "expected_bump": "major"}
+"#,
+    )
+    .unwrap();
+
+    let cases = load_dataset(&path).unwrap();
+
+    assert_eq!(cases.len(), 2);
+    assert_eq!(cases[0].current_version, "1.0.0");
+    assert_eq!(cases[0].reference_changelog.as_deref(), Some("- Add search"));
+    assert_eq!(cases[1].expected_bump, BumpType::Major);
+    assert_eq!(cases[1].commits().len(), 2);
+    // Commit IDs don't depend on the position of the case in the dataset
+    assert_eq!(cases[1].commits(), cases[1].clone().commits());
+    assert_eq!(cases[1].commits()[0].oid.len(), 40);
+}
+
+#[test]
+fn test_load_dataset_rejects_invalid_cases() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("cases.jsonl");
+    for (content, reason) in [
+        (r#"{"name": "a", "commits": ["fix: x"], "expected_bump": "huge"}"#, "line 1"),
+        (r#"{"name": "a", "commits": [], "expected_bump": "patch"}"#, "no commits"),
+        (r#"{"name": "a", "current_version": "v1", "commits": ["fix: x"], "expected_bump": "patch"}"#, "semantic version"),
+        (
+            "{\"name\": \"a\", \"commits\": [\"fix: x\"], \"expected_bump\": \"patch\"}\n{\"name\": \"a\", \"commits\": [\"fix: y\"], \"expected_bump\": \"patch\"}",
+            "earlier case",
+        ),
+        ("\n", "has no cases"),
+    ] {
+        fs::write(&path, content).unwrap();
+        let err = load_dataset(&path).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(CommitSenseError::Config(_))), "{}", content);
+        assert!(err.to_string().contains(reason), "{}: {}", reason, err);
+    }
+}
+
+#[test]
+fn test_report_counts_accuracy_confusion_and_under_bumps() {
+    let mut with_reference = case("search", BumpType::Minor);
+    with_reference.reference_changelog = Some("### Features\n- Add search".to_string());
+    let results = vec![
+        CaseResult::new(&with_reference, Ok(suggestion("minor"))),
+        CaseResult::new(&case("api", BumpType::Major), Ok(suggestion("minor"))),
+        CaseResult::new(&case("typo", BumpType::None), Ok(suggestion("patch"))),
+        CaseResult::new(&case("timeout", BumpType::Patch), Err(CommitSenseError::ApiTimeout("slow".to_string()).into())),
+    ];
+
+    let report = EvalReport::new(results);
+
+    assert_eq!((report.total, report.correct), (4, 1));
+    assert_eq!(report.accuracy, 0.25);
+    assert_eq!(report.count(BumpType::Minor, BumpType::Minor), 1);
+    assert_eq!(report.count(BumpType::Major, BumpType::Minor), 1);
+    assert_eq!(report.count(BumpType::None, BumpType::Patch), 1);
+    assert_eq!(report.count(BumpType::Patch, BumpType::Patch), 0);
+    assert_eq!(report.under_bumps, vec!["api"]);
+    assert_eq!(report.over_bumps, vec!["typo"]);
+    assert_eq!(report.failures, vec!["timeout"]);
+    assert_eq!(report.mean_changelog_overlap, Some(1.0));
+    assert_eq!(report.cases[0].model.as_deref(), Some("conventional"));
+
+    let rendered = report.render();
+    assert!(rendered.contains("Accuracy: 25.0% (1/4 cases)"), "{}", rendered);
+    assert!(rendered.contains("- api: expected major, suggested minor"), "{}", rendered);
+    assert!(rendered.contains("- timeout: "), "{}", rendered);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["confusion_matrix"]["major"]["minor"], 1);
+    assert_eq!(json["cases"][3]["predicted"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_word_overlap() {
+    assert_eq!(word_overlap("- Add **search**", "Add search."), 1.0);
+    assert_eq!(word_overlap("Fix crash", "Add search"), 0.0);
+    assert_eq!(word_overlap("", ""), 1.0);
+    // 1 shared word: precision 1/2, recall 1/4
+    let overlap = word_overlap("add search", "add a search index");
+    assert!((overlap - 2.0 / 3.0).abs() < 1e-9, "{}", overlap);
+}
+
+/// Answers every request with the same reply, like a live model.
+struct LiveProvider;
+
+#[async_trait]
+impl LlmProvider for LiveProvider {
+    fn name(&self) -> &str {
+        "live"
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        Ok(CompletionResponse {
+            content: r#"{"bump": "patch", "next_version": "1.0.1", "changelog": "- Fix crash"}"#.to_string(),
+            usage: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_cases_can_be_replayed() {
+    let dir = tempdir().unwrap();
+    let case = case("crash", BumpType::Patch);
+
+    let live = RecordingProvider::new(Box::new(LiveProvider), dir.path());
+    let recording = OpenAIClient::with_provider(Box::new(live), "gpt-4o".to_string());
+    recording
+        .get_version_and_changelog_for_commits(&case.current_version, &case.commits(), ProjectType::Rust)
+        .await
+        .unwrap();
+
+    let replay = OpenAIClient::with_provider(Box::new(ReplayProvider::new(dir.path())), "gpt-4o".to_string());
+    let outcome = replay
+        .get_version_and_changelog_for_commits(&case.current_version, &case.commits(), ProjectType::Rust)
+        .await;
+    let result = CaseResult::new(&case, outcome);
+
+    assert!(result.is_correct(), "{:?}", result);
+    assert_eq!(result.model.as_deref(), Some("gpt-4o"));
+}
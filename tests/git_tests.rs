@@ -63,10 +63,8 @@ fn test_is_git_repo_positive() -> Result<()> {
     // List files in the directory
     println!("Files in repo dir:");
     if let Ok(entries) = std::fs::read_dir(&repo_path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                println!("  {:?}", entry.path());
-            }
+        for entry in entries.flatten() {
+            println!("  {:?}", entry.path());
         }
     }
     
@@ -115,9 +113,14 @@ impl GitCommandExecutor for MockGitCommandExecutor {
                     if arg.contains("%s") {
                         return Ok("feat: Add new feature\nfix: Fix bug\ndocs: Update README".to_string());
                     }
-                    // More complex format with body
-                    if arg.contains("%B") {
-                        return Ok("feat: Add new feature\n\nThis is the body\n<EOM>\nfix: Fix bug\n<EOM>\ndocs: Update README\n<EOM>".to_string());
+                    // NUL-delimited commits with hash, parents, author, dates, trailers and message
+                    if arg.contains("%B") && args.contains(&"-z".to_string()) {
+                        return Ok([
+                            "1111111111111111111111111111111111111111\x00\x00Ada\x00ada@example.com\x002025-04-01T10:00:00+02:00\x002025-04-01T10:05:00+02:00\x00\x00feat: Add new feature\n\nThis is the body\n\x00",
+                            "2222222222222222222222222222222222222222\x001111111111111111111111111111111111111111\x00Bob\x00bob@example.com\x002025-04-02T09:00:00+00:00\x002025-04-02T09:00:00+00:00\x00Signed-off-by: Bob <bob@example.com>\nFixes: #12\n\x00fix: Fix bug\n<EOM>\nStill the same commit\n\nSigned-off-by: Bob <bob@example.com>\nFixes: #12\n\x00",
+                            "3333333333333333333333333333333333333333\x002222222222222222222222222222222222222222 4444444444444444444444444444444444444444\x00Cy\x00cy@example.com\x002025-04-03T08:00:00-05:00\x002025-04-03T08:00:00-05:00\x00\x00Merge branch 'docs'\n\x00",
+                        ]
+                        .concat());
                     }
                 }
             }
@@ -154,14 +157,35 @@ fn test_get_commits_since_oid() {
     let git = GitInterface::new(Box::new(executor));
     
     let path = PathBuf::from("/fake/path");
-    let result = git.get_commits_since_oid(&path, "1234567", "%s");
+    let result = git.get_commits_since_oid(&path, "1234567");
     
     assert!(result.is_ok());
     let commits = result.unwrap();
     assert_eq!(commits.len(), 3);
-    assert_eq!(commits[0], "feat: Add new feature");
-    assert_eq!(commits[1], "fix: Fix bug");
-    assert_eq!(commits[2], "docs: Update README");
+    assert_eq!(commits[0].oid, "1".repeat(40));
+    assert_eq!(commits[0].message, "feat: Add new feature\n\nThis is the body");
+    assert_eq!(commits[0].author_name, "Ada");
+    assert_eq!(commits[0].author_email, "ada@example.com");
+    assert_eq!(commits[0].author_date.unwrap().to_rfc3339(), "2025-04-01T10:00:00+02:00");
+    assert_eq!(commits[0].committer_date.unwrap().to_rfc3339(), "2025-04-01T10:05:00+02:00");
+    assert!(commits[0].parents.is_empty());
+    // A line that looks like the old end-of-message marker doesn't split the commit
+    assert_eq!(commits[1].subject(), "fix: Fix bug");
+    assert!(commits[1].message.contains("<EOM>\nStill the same commit"));
+    assert_eq!(commits[1].parents, vec!["1".repeat(40)]);
+    assert_eq!(commits[1].trailer_values("signed-off-by").collect::<Vec<_>>(), vec!["Bob <bob@example.com>"]);
+    assert_eq!(commits[1].trailer_values("Fixes").collect::<Vec<_>>(), vec!["#12"]);
+    assert!(commits[2].is_merge());
+    assert!(!commits[1].is_merge());
+}
+
+#[test]
+fn test_get_commits_since_head_is_empty() {
+    let git = GitInterface::new(Box::new(MockGitCommandExecutor));
+
+    let commits = git.get_commits_since_oid(Path::new("/fake/path"), "abcdef1234567890").unwrap();
+
+    assert!(commits.is_empty());
 }
 
 #[test]
@@ -187,3 +211,74 @@ fn test_find_latest_version_tag_with_pattern() {
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "v1.0.0");
 }
+
+#[test]
+fn test_commits_since_oid_order_and_git_dir() -> Result<()> {
+    let (dir, repo_path) = setup_mock_git_repo()?;
+    let base_oid = get_latest_commit_oid_cli(&repo_path)?;
+
+    for name in ["a.txt", "b.txt"] {
+        std::fs::write(repo_path.join(name), name)?;
+        Command::new("git").args(["add", name]).current_dir(&repo_path).status()?;
+        Command::new("git")
+            .args(["commit", "-m", &format!("Add {}", name)])
+            .current_dir(&repo_path)
+            .status()?;
+    }
+
+    let commits = get_commits_since_oid(&repo_path, &base_oid)?;
+    assert_eq!(commits.len(), 2);
+    // Oldest first
+    assert_eq!(commits[0].message, "Add a.txt");
+    assert_eq!(commits[1].oid, get_latest_commit_oid_cli(&repo_path)?);
+
+    let git_dir = get_git_dir(&repo_path)?;
+    assert_eq!(git_dir.canonicalize()?, repo_path.join(".git").canonicalize()?);
+
+    let _ = &dir;
+    Ok(())
+}
+
+fn get_latest_commit_oid_cli(path: &Path) -> Result<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).current_dir(path).output()?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+#[test]
+fn test_commits_since_oid_keep_hashes() -> Result<()> {
+    let (dir, repo_path) = setup_mock_git_repo()?;
+    let base_oid = get_latest_commit_oid_cli(&repo_path)?;
+
+    std::fs::write(repo_path.join("a.txt"), "a")?;
+    Command::new("git").args(["add", "a.txt"]).current_dir(&repo_path).status()?;
+    Command::new("git")
+        .args(["commit", "-m", "feat: add a\n\nWith a body."])
+        .current_dir(&repo_path)
+        .status()?;
+    let first_oid = get_latest_commit_oid_cli(&repo_path)?;
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "fix: keep <EOM>\n<EOM>\nintact\n\nCo-authored-by: Other\n  Person <other@foxycorps.com>"])
+        .current_dir(&repo_path)
+        .status()?;
+
+    let commits = get_commits_since_oid(&repo_path, &base_oid)?;
+    assert_eq!(commits.len(), 2);
+    assert_eq!(commits[0].oid, first_oid);
+    assert_eq!(commits[0].message, "feat: add a\n\nWith a body.");
+    assert_eq!(commits[0].subject(), "feat: add a");
+    assert_eq!(commits[0].short_oid(), &commits[0].oid[..7]);
+    assert_eq!(commits[0].parents, vec![base_oid.clone()]);
+    assert_eq!(commits[0].author_name, "Test User");
+    assert_eq!(commits[0].author_email, "test@foxycorps.com");
+    assert!(commits[0].author_date.is_some());
+    assert!(commits[0].trailers.is_empty());
+    assert_eq!(commits[1].parents, vec![first_oid]);
+    assert!(commits[1].message.starts_with("fix: keep <EOM>\n<EOM>\nintact"));
+    assert_eq!(
+        commits[1].trailer_values("Co-authored-by").collect::<Vec<_>>(),
+        vec!["Other Person <other@foxycorps.com>"]
+    );
+
+    let _ = &dir;
+    Ok(())
+}
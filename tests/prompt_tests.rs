@@ -0,0 +1,97 @@
+use anyhow::Result;
+use commit_sense::prompt::*;
+use commit_sense::ProjectType;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_default_template_renders_builtin_prompt() {
+    let messages = PromptTemplate::default().render("1.2.3", ProjectType::Rust, "---\nFix crash\n---");
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].role, "system");
+    assert!(messages[0].content.contains("(currently version 1.2.3) for a Rust project"));
+    assert!(messages[0].content.contains(SEMVER_RULES));
+    assert!(messages[0].content.contains(DEFAULT_CHANGELOG_STYLE));
+    assert!(messages[0].content.contains("\"bump\": \"major|minor|patch|none\""));
+    assert!(!messages[0].content.contains("{{"));
+    assert_eq!(messages[1].role, "user");
+    assert!(messages[1].content.starts_with("Current version: 1.2.3\nProject type: Rust"));
+    assert!(messages[1].content.contains("---\nFix crash\n---"));
+}
+
+#[test]
+fn test_custom_template_with_user_section() -> Result<()> {
+    let template = PromptTemplate::parse(
+        "Release notes for {{ project_type }} {{current_version}}.\nPerf changes are minor.\n\n{{changelog_style}}\n\n<!-- user -->\nCommits:\n{{commits}}\n",
+    )
+    .map_err(anyhow::Error::msg)?
+    .with_changelog_style("Write one line per change.");
+    assert!(template.is_custom());
+
+    let messages = template.render("2.0.0", ProjectType::JavaScript, "---\nSpeed up parser\n---");
+    assert!(messages[0].content.starts_with("Release notes for JavaScript/TypeScript 2.0.0.\nPerf changes are minor."));
+    assert!(messages[0].content.contains("Write one line per change."));
+    // The answer format is always appended
+    assert!(messages[0].content.contains("Return your analysis as a JSON object"));
+    assert_eq!(messages[1].content, "Commits:\n---\nSpeed up parser\n---");
+
+    // The map step of large ranges applies the custom policy
+    assert!(template.policy("2.0.0", ProjectType::JavaScript).contains("Perf changes are minor."));
+    assert_eq!(PromptTemplate::default().policy("2.0.0", ProjectType::Rust), SEMVER_RULES);
+
+    Ok(())
+}
+
+#[test]
+fn test_template_without_user_section_uses_default_user_message() {
+    let template = PromptTemplate::parse("Changes under internal/ never count.").unwrap();
+    let messages = template.render("1.0.0", ProjectType::Rust, "---\nFix crash\n---");
+    assert!(messages[0].content.starts_with("Changes under internal/ never count."));
+    assert!(messages[1].content.contains("---\nFix crash\n---"));
+}
+
+#[test]
+fn test_placeholders_in_values_are_not_expanded() {
+    let template = PromptTemplate::parse("Policy\n<!-- user -->\n{{commits}}").unwrap();
+    let messages = template.render("1.0.0", ProjectType::Rust, "Mention {{project_type}} in docs");
+    assert_eq!(messages[1].content, "Mention {{project_type}} in docs");
+}
+
+#[test]
+fn test_invalid_templates_are_rejected() {
+    let err = PromptTemplate::parse("Version {{curent_version}}").unwrap_err();
+    assert!(err.contains("unknown placeholder '{{curent_version}}'"));
+    assert!(err.contains("{{current_version}}"));
+
+    let err = PromptTemplate::parse("Commits: {{commits}}").unwrap_err();
+    assert!(err.contains("belongs in the user message"));
+
+    let err = PromptTemplate::parse("Policy\n<!-- user -->\nNo commits here").unwrap_err();
+    assert!(err.contains("must contain the {{commits}} placeholder"));
+
+    assert!(PromptTemplate::parse("  \n<!-- user -->\n{{commits}}").is_err());
+}
+
+#[test]
+fn test_load_template_file() -> Result<()> {
+    let dir = tempdir()?;
+    let path = PromptTemplate::default_path(dir.path());
+
+    // A missing optional file means the built-in prompt; a missing explicit file is an error
+    assert_eq!(PromptTemplate::load(&path, false)?, PromptTemplate::default());
+    assert!(PromptTemplate::load(&path, true).is_err());
+
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, "Our policy.\n<!-- user -->\n{{commits}}\n")?;
+    assert!(PromptTemplate::load(&path, true)?.is_custom());
+
+    fs::write(&path, "Our policy for {{project}}.")?;
+    let err = PromptTemplate::load(&path, true).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<commit_sense::CommitSenseError>().map(|e| e.exit_code()),
+        Some(2)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use anyhow::Result;
+use commit_sense::cli::ProviderKind;
+use commit_sense::config::ProviderSettings;
+use commit_sense::openai::*;
+use commit_sense::provider::*;
+use commit_sense::ProjectType;
+use mockito::{Matcher, Server};
+
+#[test]
+fn test_provider_kind_parsing() {
+    assert_eq!("openai".parse::<ProviderKind>(), Ok(ProviderKind::OpenAi));
+    assert_eq!("Ollama".parse::<ProviderKind>(), Ok(ProviderKind::Ollama));
+    assert_eq!("llama.cpp".parse::<ProviderKind>(), Ok(ProviderKind::LlamaCpp));
+    assert_eq!("azure-openai".parse::<ProviderKind>(), Ok(ProviderKind::Azure));
+    assert!("bard".parse::<ProviderKind>().is_err());
+}
+
+#[test]
+fn test_create_provider_requires_key_for_openai_only() {
+    assert!(create_provider(ProviderKind::OpenAi, None, None).is_err());
+    assert!(create_provider(ProviderKind::OpenAi, None, Some("")).is_err());
+    assert!(create_provider(ProviderKind::OpenAi, None, Some("sk-test")).is_ok());
+    assert!(create_provider(ProviderKind::Ollama, None, None).is_ok());
+    assert!(create_provider(ProviderKind::LlamaCpp, None, None).is_ok());
+}
+
+#[tokio::test]
+async fn test_ollama_provider_end_to_end() -> Result<()> {
+    // Setup mock Ollama server
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/api/chat")
+        .match_header("authorization", Matcher::Missing)
+        .match_body(Matcher::PartialJsonString(r#"{"model": "llama3", "stream": false}"#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{
+            "model": "llama3",
+            "message": {
+                "role": "assistant",
+                "content": "{\"bump\": \"minor\", \"next_version\": \"0.3.0\", \"changelog\": \"- Add offline mode\"}"
+            },
+            "done": true
+        }"#)
+        .create_async()
+        .await;
+
+    let provider = create_provider(ProviderKind::Ollama, Some(&server.url()), None)?;
+    let client = OpenAIClient::with_provider(provider, "llama3".to_string());
+
+    let commits = vec!["feat: Add offline mode".to_string()];
+    let suggestion = client.get_version_and_changelog("0.2.7", &commits, ProjectType::Rust).await?;
+
+    assert_eq!(suggestion.bump_type, "minor");
+    assert_eq!(suggestion.next_version, "0.3.0");
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_llamacpp_provider_flattens_prompt() -> Result<()> {
+    // Setup mock llama.cpp server
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/completion")
+        .match_body(Matcher::Regex(r#""prompt":"System: .*User: .*fix: Correct rounding.*Assistant:""#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"content": "{\"bump\": \"patch\", \"next_version\": \"1.0.1\", \"changelog\": \"- Fix rounding\"}", "stop": true}"#)
+        .create_async()
+        .await;
+
+    let provider = LlamaCppProvider::new(server.url(), None);
+    let client = OpenAIClient::with_provider(Box::new(provider), "local".to_string());
+
+    let commits = vec!["fix: Correct rounding".to_string()];
+    let suggestion = client.get_version_and_changelog("1.0.0", &commits, ProjectType::JavaScript).await?;
+
+    assert_eq!(suggestion.bump_type, "patch");
+    assert_eq!(suggestion.next_version, "1.0.1");
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_is_unreachable_for_connection_errors() -> Result<()> {
+    // Nothing listens on port 1, so the connection is refused
+    let provider = create_provider(ProviderKind::Ollama, Some("http://127.0.0.1:1"), None)?;
+    let client = OpenAIClient::with_provider(provider, "llama3".to_string());
+
+    let commits = vec!["feat: Add offline mode".to_string()];
+    let err = client.get_version_and_changelog("0.2.7", &commits, ProjectType::Rust).await.unwrap_err();
+    assert!(is_unreachable(&err));
+
+    // A server that answers with an error is reachable
+    let mut server = Server::new_async().await;
+    let _mock = server.mock("POST", "/api/chat").with_status(400).create_async().await;
+    let provider = create_provider(ProviderKind::Ollama, Some(&server.url()), None)?;
+    let client = OpenAIClient::with_provider(provider, "llama3".to_string());
+    let err = client.get_version_and_changelog("0.2.7", &commits, ProjectType::Rust).await.unwrap_err();
+    assert!(!is_unreachable(&err));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_providers_report_token_usage() -> Result<()> {
+    let mut server = Server::new_async().await;
+    server.mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_body(r#"{"choices": [{"message": {"content": "hi"}}], "usage": {"prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150}}"#)
+        .create_async()
+        .await;
+    server.mock("POST", "/api/chat")
+        .with_status(200)
+        .with_body(r#"{"message": {"role": "assistant", "content": "hi"}, "done": true, "prompt_eval_count": 80, "eval_count": 12}"#)
+        .create_async()
+        .await;
+    server.mock("POST", "/completion")
+        .with_status(200)
+        .with_body(r#"{"content": "hi"}"#)
+        .create_async()
+        .await;
+    let request = CompletionRequest {
+        model: "m".to_string(),
+        messages: vec![ChatMessage::user("hello")],
+        ..Default::default()
+    };
+
+    let openai = OpenAiCompatibleProvider::new(server.url(), "sk-test").complete(&request).await?;
+    assert_eq!(openai.usage, Some(TokenUsage { prompt_tokens: 120, completion_tokens: 30 }));
+    let ollama = OllamaProvider::new(server.url(), None).complete(&request).await?;
+    assert_eq!(ollama.usage, Some(TokenUsage { prompt_tokens: 80, completion_tokens: 12 }));
+    // Responses without counts are reported as such rather than as zero
+    let llamacpp = LlamaCppProvider::new(server.url(), None).complete(&request).await?;
+    assert_eq!(llamacpp.usage, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_azure_deployment_with_extra_headers() -> Result<()> {
+    std::env::set_var("COMMITSENSE_TEST_GATEWAY_TEAM", "release-eng");
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/openai/deployments/prod-gpt4o/chat/completions")
+        .match_query(Matcher::UrlEncoded("api-version".to_string(), "2024-10-21".to_string()))
+        .match_header("api-key", "azure-secret")
+        .match_header("authorization", Matcher::Missing)
+        .match_header("x-team", "team=release-eng")
+        .with_status(200)
+        .with_body(r#"{"choices": [{"message": {"content": "hi"}}]}"#)
+        .create_async()
+        .await;
+    let settings = ProviderSettings {
+        deployment: Some("prod-gpt4o".to_string()),
+        headers: [("X-Team".to_string(), "team=${COMMITSENSE_TEST_GATEWAY_TEAM}".to_string())].into(),
+        ..Default::default()
+    };
+
+    let provider = create_provider_with_settings(ProviderKind::Azure, Some(&server.url()), Some("azure-secret"), &settings)?;
+    assert_eq!(provider.name(), "azure");
+    let request = CompletionRequest {
+        model: "gpt-4o".to_string(),
+        messages: vec![ChatMessage::user("hello")],
+        ..Default::default()
+    };
+    assert_eq!(provider.complete(&request).await?.content, "hi");
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn test_provider_settings_are_checked() {
+    let azure = ProviderSettings {
+        deployment: Some("prod".to_string()),
+        ..Default::default()
+    };
+    // Azure needs the resource URL and a deployment
+    assert!(create_provider_with_settings(ProviderKind::Azure, None, Some("key"), &azure).is_err());
+    assert!(create_provider_with_settings(ProviderKind::Azure, Some("https://x.openai.azure.com"), Some("key"), &ProviderSettings::default()).is_err());
+    assert!(create_provider_with_settings(ProviderKind::Azure, Some("https://x.openai.azure.com"), Some("key"), &azure).is_ok());
+    // A deployment makes no sense for other providers
+    assert!(create_provider_with_settings(ProviderKind::OpenAi, None, Some("key"), &azure).is_err());
+    assert!(create_provider_with_settings(ProviderKind::Ollama, None, None, &azure).is_err());
+
+    let missing_var = ProviderSettings {
+        headers: [("X-Key".to_string(), "${COMMITSENSE_TEST_UNSET_VAR}".to_string())].into(),
+        ..Default::default()
+    };
+    let err = create_provider_with_settings(ProviderKind::OpenAi, None, Some("key"), &missing_var).map(|_| ()).unwrap_err();
+    assert!(err.to_string().contains("'COMMITSENSE_TEST_UNSET_VAR', which is not set"));
+    let bad_name = ProviderSettings {
+        headers: [("X Key".to_string(), "v".to_string())].into(),
+        ..Default::default()
+    };
+    assert!(create_provider_with_settings(ProviderKind::OpenAi, None, Some("key"), &bad_name).is_err());
+}
+
+#[tokio::test]
+async fn test_gateway_auth_header_and_api_version() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("POST", "/v1/chat/completions")
+        .match_query(Matcher::UrlEncoded("api-version".to_string(), "2025-01-01".to_string()))
+        .match_header("x-litellm-key", "sk-proxy")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(200)
+        .with_body(r#"{"choices": [{"message": {"content": "hi"}}]}"#)
+        .create_async()
+        .await;
+    let settings = ProviderSettings {
+        api_version: Some("2025-01-01".to_string()),
+        auth_header: Some("X-LiteLLM-Key".to_string()),
+        ..Default::default()
+    };
+
+    let provider = create_provider_with_settings(ProviderKind::OpenAi, Some(&format!("{}/v1", server.url())), Some("sk-proxy"), &settings)?;
+    let request = CompletionRequest {
+        model: "m".to_string(),
+        messages: vec![ChatMessage::user("hello")],
+        ..Default::default()
+    };
+    provider.complete(&request).await?;
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sampling_settings_in_request_bodies() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let openai = server.mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJsonString(r#"{"temperature": 0.5, "seed": 7, "max_tokens": 500}"#.to_string()))
+        .with_status(200)
+        .with_body(r#"{"choices": [{"message": {"content": "hi"}}]}"#)
+        .create_async()
+        .await;
+    let ollama = server.mock("POST", "/api/chat")
+        .match_body(Matcher::PartialJsonString(r#"{"options": {"temperature": 0.5, "seed": 7, "num_predict": 500}}"#.to_string()))
+        .with_status(200)
+        .with_body(r#"{"message": {"role": "assistant", "content": "hi"}, "done": true}"#)
+        .create_async()
+        .await;
+    let llamacpp = server.mock("POST", "/completion")
+        .match_body(Matcher::PartialJsonString(r#"{"temperature": 0.5, "seed": 7, "n_predict": 500}"#.to_string()))
+        .with_status(200)
+        .with_body(r#"{"content": "hi"}"#)
+        .create_async()
+        .await;
+    let request = CompletionRequest {
+        model: "m".to_string(),
+        messages: vec![ChatMessage::user("hello")],
+        temperature: Some(0.5),
+        seed: Some(7),
+        max_tokens: Some(500),
+        ..Default::default()
+    };
+
+    OpenAiCompatibleProvider::new(server.url(), "sk-test").complete(&request).await?;
+    OllamaProvider::new(server.url(), None).complete(&request).await?;
+    LlamaCppProvider::new(server.url(), None).complete(&request).await?;
+    openai.assert_async().await;
+    ollama.assert_async().await;
+    llamacpp.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn test_is_api_error() {
+    let invalid: anyhow::Error = commit_sense::CommitSenseError::ApiInvalidResponse("bad JSON".to_string()).into();
+    assert!(is_api_error(&invalid.context("Failed to get chat completion")));
+    let config: anyhow::Error = commit_sense::CommitSenseError::Config("bad".to_string()).into();
+    assert!(!is_api_error(&config));
+    assert!(!is_api_error(&anyhow::anyhow!("Current version is not a valid semantic version")));
+}
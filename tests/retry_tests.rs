@@ -0,0 +1,160 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use commit_sense::cli::ProviderKind;
+use commit_sense::provider::*;
+use commit_sense::retry::*;
+use commit_sense::CommitSenseError;
+use mockito::Server;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn fast_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(5),
+    }
+}
+
+// Provider that fails with the given error a fixed number of times before succeeding
+struct FlakyProvider {
+    failures: u32,
+    error: fn() -> CommitSenseError,
+    calls: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl LlmProvider for FlakyProvider {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call < self.failures {
+            Err((self.error)().into())
+        } else {
+            Ok(CompletionResponse { content: "ok".to_string(), usage: None })
+        }
+    }
+}
+
+fn flaky(failures: u32, error: fn() -> CommitSenseError) -> (FlakyProvider, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    (FlakyProvider { failures, error, calls: calls.clone() }, calls)
+}
+
+#[tokio::test]
+async fn test_transient_errors_are_retried() -> Result<()> {
+    let (inner, calls) = flaky(2, || CommitSenseError::ApiServer { status: 503, message: "unavailable".to_string(), retry_after: None });
+    let provider = RetryingProvider::new(Box::new(inner), fast_policy(3));
+
+    let response = provider.complete(&CompletionRequest::default()).await?;
+
+    assert_eq!(response.content, "ok");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retries_are_bounded() {
+    let (inner, calls) = flaky(10, || CommitSenseError::ApiConnection("refused".to_string()));
+    let provider = RetryingProvider::new(Box::new(inner), fast_policy(2));
+
+    let err = provider.complete(&CompletionRequest::default()).await.unwrap_err();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert!(matches!(err.root_cause().downcast_ref::<CommitSenseError>(), Some(CommitSenseError::ApiConnection(_))));
+}
+
+#[tokio::test]
+async fn test_auth_errors_are_not_retried() {
+    let (inner, calls) = flaky(1, || CommitSenseError::ApiAuth("bad key".to_string()));
+    let provider = RetryingProvider::new(Box::new(inner), fast_policy(3));
+
+    assert!(provider.complete(&CompletionRequest::default()).await.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_long_retry_after_fails_with_the_rate_limit() {
+    let (inner, calls) = flaky(1, || CommitSenseError::ApiRateLimit {
+        message: "slow down".to_string(),
+        retry_after: Some(Duration::from_secs(3600)),
+    });
+    let provider = RetryingProvider::new(Box::new(inner), fast_policy(3));
+
+    let err = provider.complete(&CompletionRequest::default()).await.unwrap_err();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let classified = err.root_cause().downcast_ref::<CommitSenseError>().expect("classified error");
+    assert!(matches!(classified, CommitSenseError::ApiRateLimit { .. }));
+    assert_eq!(classified.exit_code(), 4);
+}
+
+#[test]
+fn test_backoff_grows_and_is_capped() {
+    let policy = RetryPolicy {
+        max_retries: 5,
+        initial_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(5),
+    };
+    assert_eq!(policy.base_delay(1), Duration::from_secs(1));
+    assert_eq!(policy.base_delay(2), Duration::from_secs(2));
+    assert_eq!(policy.base_delay(3), Duration::from_secs(4));
+    assert_eq!(policy.base_delay(4), Duration::from_secs(5));
+
+    let server_error = CommitSenseError::ApiServer { status: 502, message: String::new(), retry_after: None };
+    let delay = policy.delay_for(3, &server_error).expect("server errors are retried");
+    assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+}
+
+#[test]
+fn test_retry_after_is_honored() {
+    let policy = RetryPolicy::default();
+    let rate_limited = CommitSenseError::ApiRateLimit {
+        message: String::new(),
+        retry_after: Some(Duration::from_secs(22)),
+    };
+    assert_eq!(policy.delay_for(1, &rate_limited), Some(Duration::from_secs(22)));
+    let unavailable = CommitSenseError::ApiServer {
+        status: 503,
+        message: String::new(),
+        retry_after: Some(Duration::from_secs(9)),
+    };
+    assert_eq!(policy.delay_for(1, &unavailable), Some(Duration::from_secs(9)));
+
+    assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+    assert_eq!(parse_retry_after("soon"), None);
+}
+
+#[tokio::test]
+async fn test_http_statuses_are_classified() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let cases: [(usize, &str); 4] = [(401, "/a"), (429, "/b"), (503, "/c"), (400, "/d")];
+    for (status, path) in cases {
+        server.mock("POST", format!("{}/chat/completions", path).as_str())
+            .with_status(status)
+            .with_header("retry-after", "12")
+            .create_async()
+            .await;
+    }
+
+    let mut errors = Vec::new();
+    for (_, path) in cases {
+        let provider = create_provider(ProviderKind::OpenAi, Some(&format!("{}{}", server.url(), path)), Some("key"))?;
+        let err = provider.complete(&CompletionRequest::default()).await.unwrap_err();
+        errors.push(err.downcast::<CommitSenseError>()?);
+    }
+
+    assert!(matches!(errors[0], CommitSenseError::ApiAuth(_)));
+    assert_eq!(errors[0].exit_code(), 3);
+    assert!(matches!(errors[1], CommitSenseError::ApiRateLimit { retry_after: Some(d), .. } if d == Duration::from_secs(12)));
+    assert!(matches!(errors[2], CommitSenseError::ApiServer { status: 503, retry_after: Some(d), .. } if d == Duration::from_secs(12)));
+    assert!(errors[2].is_retryable());
+    assert!(matches!(errors[3], CommitSenseError::ApiRequest(_)));
+    assert!(!errors[3].is_retryable());
+    Ok(())
+}
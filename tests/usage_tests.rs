@@ -0,0 +1,89 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use commit_sense::provider::*;
+use commit_sense::usage::*;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_model_price_lookup_and_overrides() {
+    let mini = ModelPrice::for_model("gpt-4o-mini-2024-07-18", None, None).expect("built-in price");
+    assert_eq!(mini.input_per_million, 0.15);
+    let gpt4o = ModelPrice::for_model("gpt-4o", None, None).expect("built-in price");
+    assert_eq!(gpt4o.output_per_million, 10.0);
+    assert!(ModelPrice::for_model("llama3.1:8b", None, None).is_none());
+    // A configured price replaces the built-in one, and prices models without one
+    let custom = ModelPrice::for_model("llama3.1:8b", Some(0.0), Some(0.0)).expect("configured price");
+    assert_eq!(custom.cost(&TokenUsage { prompt_tokens: 1_000, completion_tokens: 1_000 }), 0.0);
+    assert!(ModelPrice::for_model("llama3.1:8b", Some(1.0), None).is_none());
+
+    let cost = gpt4o.cost(&TokenUsage { prompt_tokens: 200_000, completion_tokens: 10_000 });
+    assert!((cost - 0.6).abs() < 1e-9);
+}
+
+// Provider that answers every request with the same usage (or none)
+struct UsageProvider(Option<TokenUsage>);
+
+#[async_trait]
+impl LlmProvider for UsageProvider {
+    fn name(&self) -> &str {
+        "usage"
+    }
+
+    async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+        Ok(CompletionResponse { content: "{}".to_string(), usage: self.0 })
+    }
+}
+
+#[tokio::test]
+async fn test_metered_provider_sums_usage() -> Result<()> {
+    let meter = UsageMeter::default();
+    let usage = TokenUsage { prompt_tokens: 100, completion_tokens: 20 };
+    let reporting = MeteredProvider::new(Box::new(UsageProvider(Some(usage))), meter.clone());
+    let silent = MeteredProvider::new(Box::new(UsageProvider(None)), meter.clone());
+    let request = CompletionRequest::default();
+
+    reporting.complete(&request).await?;
+    reporting.complete(&request).await?;
+    silent.complete(&request).await?;
+
+    let totals = meter.totals();
+    assert_eq!(totals.requests, 3);
+    assert_eq!(totals.tokens, TokenUsage { prompt_tokens: 200, completion_tokens: 40 });
+    assert_eq!(totals.unreported_requests, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_usage_log_appends_json_lines() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("usage.jsonl");
+    let record = UsageRecord {
+        timestamp: "2025-01-01T00:00:00+00:00".to_string(),
+        repository: "foxycorps/commit-sense".to_string(),
+        provider: "openai".to_string(),
+        model: "gpt-4o".to_string(),
+        usage: RunUsage {
+            requests: 1,
+            tokens: TokenUsage { prompt_tokens: 100, completion_tokens: 20 },
+            unreported_requests: 0,
+        },
+        total_tokens: 120,
+        estimated_cost: Some(0.00045),
+    };
+
+    append_usage_log(&path, &record)?;
+    append_usage_log(&path, &record)?;
+
+    let content = fs::read_to_string(&path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let value: serde_json::Value = serde_json::from_str(lines[0])?;
+    assert_eq!(value["repository"], "foxycorps/commit-sense");
+    assert_eq!(value["prompt_tokens"], 100);
+    assert_eq!(value["total_tokens"], 120);
+    assert_eq!(serde_json::from_str::<UsageRecord>(lines[1])?, record);
+
+    Ok(())
+}
@@ -90,3 +90,15 @@ fn test_create_nightly_version() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_bump_type_parsing_and_ordering() {
+    use commit_sense::version::BumpType;
+
+    assert_eq!("MAJOR".parse::<BumpType>(), Ok(BumpType::Major));
+    assert_eq!("none".parse::<BumpType>(), Ok(BumpType::None));
+    assert!("huge".parse::<BumpType>().is_err());
+    assert!(BumpType::Major > BumpType::Minor);
+    assert!(BumpType::Patch > BumpType::None);
+    assert_eq!(BumpType::Minor.to_string(), "minor");
+}